@@ -44,9 +44,28 @@ fn read_config(dirs: &ProjectDirs) -> Result<Config> {
 }
 
 fn write_config(dirs: &ProjectDirs, config: &Config) -> Result<()> {
-    let data = toml::to_vec(&config)?;
+    let data = serialize_config(config)?;
     fs::create_dir_all(dirs.config_dir())?;
     let config_file = dirs.config_dir().join(CONFIG_FILE);
     fs::write(&config_file, &data)?;
     Ok(())
 }
+
+/// Serialize the config through a TOML value, which puts plain values
+/// before tables as TOML requires, whatever the order of the fields.
+fn serialize_config(config: &Config) -> Result<Vec<u8>> {
+    let value = toml::Value::try_from(config)?;
+    Ok(toml::to_vec(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_round_trips() {
+        let data = serialize_config(&Config::default()).unwrap();
+        let config: Config = toml::from_slice(&data).unwrap();
+        assert_eq!(serialize_config(&config).unwrap(), data);
+    }
+}
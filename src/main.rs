@@ -1,59 +1,108 @@
 use crate::config::AppConfig;
-use crate::sampler::TaskContext;
-use crate::ui::{DefaultConfig, Progress, UiOpt, UiRes};
+use crate::plugins::MissingPlugins;
+use crate::probe::ProbeResult;
+use crate::sampler::{SamplerConfig, TaskContext};
+use crate::ui::{DefaultConfig, Progress, TaskState, UiOpt, UiRes};
 use anyhow::Result;
+use gst_pbutils::Discoverer;
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
 use std::thread;
+use url::Url;
 
 mod config;
+mod plugins;
+mod preview;
+mod probe;
 mod res;
 mod sampler;
+mod scan;
 mod ui;
 
 #[derive(Default, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Config {
     default: DefaultConfig,
+    sampler: SamplerConfig,
 }
 
 fn main() -> Result<()> {
     gtk::init()?;
     gst::init()?;
+    plugins::init();
 
     let _res_holder = res::load()?;
     let app_config = AppConfig::load();
+    let sampler_config = app_config.config.borrow().sampler;
     let (task_sender, task_receiver) = crossbeam_channel::unbounded();
-    let UiRes { progress_sender } = ui::init(UiOpt {
+    let (probe_sender, probe_receiver) = crossbeam_channel::unbounded();
+    let UiRes {
+        progress_sender,
+        probe_result_sender,
+    } = ui::init(UiOpt {
         task_sender,
+        probe_sender,
         config: app_config.config.clone(),
+        sources: env::args_os().skip(1).filter_map(arg_to_uri).collect(),
+    });
+
+    let discoverer = Discoverer::new(sampler_config.probe_timeout().into())?;
+    thread::spawn(move || {
+        for uri in probe_receiver.iter() {
+            let result = probe::probe(&discoverer, &uri);
+            let _ = probe_result_sender.send(ProbeResult { uri, result });
+        }
     });
 
     for _ in 0..num_cpus::get_physical() {
         let task_receiver = task_receiver.clone();
         let progress_sender = progress_sender.clone();
         thread::spawn(move || {
-            let mut ctx = TaskContext {
-                rng: rand::thread_rng(),
-            };
+            let mut ctx = TaskContext::new(sampler_config);
             loop {
                 let task = match task_receiver.recv() {
                     Ok(task) => task,
                     Err(_) => break,
                 };
                 let ref_idx = task.ref_idx;
-                let result = sampler::run_task(&mut ctx, task, |p| {
-                    let _ = progress_sender.send(Progress {
-                        ref_idx,
-                        progress: Some(p),
-                    });
-                });
-                if let Err(e) = result {
-                    eprintln!("error: {:?}", e);
-                    let _ = progress_sender.send(Progress {
-                        ref_idx,
-                        progress: None,
-                    });
-                }
+                let result = sampler::run_task(
+                    &mut ctx,
+                    task,
+                    |p| {
+                        let _ = progress_sender.send(Progress {
+                            ref_idx,
+                            state: TaskState::Running(p),
+                        });
+                    },
+                    |path| {
+                        let _ = progress_sender.send(Progress {
+                            ref_idx,
+                            state: TaskState::Sampled(path.to_owned()),
+                        });
+                    },
+                );
+                let state = match result {
+                    Ok(result) => {
+                        for warning in result.warnings.iter() {
+                            eprintln!("warning: {}", warning);
+                        }
+                        TaskState::Finished(result.warnings)
+                    }
+                    Err(e) => {
+                        eprintln!("error: {:?}", e);
+                        let missing_plugins = e
+                            .downcast_ref::<MissingPlugins>()
+                            .map(MissingPlugins::installer_details)
+                            .unwrap_or_default();
+                        TaskState::Failed {
+                            reason: format!("{:#}", e),
+                            missing_plugins,
+                        }
+                    }
+                };
+                let _ = progress_sender.send(Progress { ref_idx, state });
             }
         });
     }
@@ -62,3 +111,16 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Convert a command line argument to a URI. Anything which doesn't look
+/// like a URI is taken as a local path.
+fn arg_to_uri(arg: OsString) -> Option<String> {
+    if let Some(url) = arg.to_str().and_then(|arg| Url::parse(arg).ok()) {
+        // Single letter schemes are likely Windows drive letters.
+        if url.scheme().len() > 1 {
+            return Some(url.into());
+        }
+    }
+    let path = fs::canonicalize(&arg).ok()?;
+    Url::from_file_path(path).ok().map(String::from)
+}
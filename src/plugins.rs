@@ -0,0 +1,126 @@
+//! Support for plugins missing from the GStreamer installation.
+//!
+//! The Rust bindings don't cover the missing plugin utilities from
+//! gst-plugins-base, so this calls into them directly.
+
+use glib::glib_sys::gpointer;
+use glib::translate::{from_glib, from_glib_full};
+use gst::Message;
+use gst_pbutils_sys::*;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::c_char;
+use std::ptr;
+
+pub fn init() {
+    unsafe { gst_pb_utils_init() }
+}
+
+/// A plugin which an element reported as missing.
+#[derive(Debug)]
+pub struct MissingPlugin {
+    /// Human readable description of the missing codec or container.
+    pub description: String,
+    /// Detail string to be passed to the installer.
+    pub installer_detail: Option<String>,
+}
+
+impl MissingPlugin {
+    /// Parse the message if it is a missing-plugin message.
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        unsafe {
+            let msg = msg.as_ptr() as *mut _;
+            let is_missing_plugin: bool = from_glib(gst_is_missing_plugin_message(msg));
+            if !is_missing_plugin {
+                return None;
+            }
+            let description = from_glib_full(gst_missing_plugin_message_get_description(msg));
+            let installer_detail =
+                from_glib_full(gst_missing_plugin_message_get_installer_detail(msg));
+            Some(MissingPlugin {
+                description,
+                installer_detail,
+            })
+        }
+    }
+}
+
+/// Failure of a task caused by missing plugins.
+#[derive(Debug)]
+pub struct MissingPlugins(pub Vec<MissingPlugin>);
+
+impl MissingPlugins {
+    pub fn installer_details(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .filter_map(|plugin| plugin.installer_detail.clone())
+            .collect()
+    }
+}
+
+impl fmt::Display for MissingPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("missing plugins: ")?;
+        for (i, plugin) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(&plugin.description)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingPlugins {}
+
+/// Whether the platform provides a helper for installing plugins.
+pub fn installation_supported() -> bool {
+    unsafe { from_glib(gst_install_plugins_supported()) }
+}
+
+/// Start installing the plugins with the given installer details.
+///
+/// The callback is invoked from the main loop with whether the plugins
+/// have been installed. Returns false if the installer can't be started.
+pub fn install<F: FnOnce(bool) + 'static>(details: &[String], callback: F) -> bool {
+    let details = details
+        .iter()
+        .filter_map(|detail| CString::new(detail.as_str()).ok())
+        .collect::<Vec<_>>();
+    let mut detail_ptrs = details
+        .iter()
+        .map(|detail| detail.as_ptr())
+        .collect::<Vec<*const c_char>>();
+    detail_ptrs.push(ptr::null());
+
+    unsafe extern "C" fn trampoline<F: FnOnce(bool) + 'static>(
+        result: GstInstallPluginsReturn,
+        user_data: gpointer,
+    ) {
+        let callback = Box::from_raw(user_data as *mut F);
+        let succeeded = matches!(
+            result,
+            GST_INSTALL_PLUGINS_SUCCESS | GST_INSTALL_PLUGINS_PARTIAL_SUCCESS
+        );
+        if succeeded {
+            // Make the new plugins visible to pipelines created from now on.
+            let _ = gst::update_registry();
+        }
+        callback(succeeded);
+    }
+
+    let callback = Box::into_raw(Box::new(callback));
+    let result = unsafe {
+        gst_install_plugins_async(
+            detail_ptrs.as_ptr(),
+            ptr::null_mut(),
+            Some(trampoline::<F>),
+            callback as gpointer,
+        )
+    };
+    if result != GST_INSTALL_PLUGINS_STARTED_OK {
+        drop(unsafe { Box::from_raw(callback) });
+        return false;
+    }
+    true
+}
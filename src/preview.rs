@@ -0,0 +1,123 @@
+//! Preview of videos for picking frames by hand.
+
+use crate::sampler::{sample_position, RawFrame};
+use anyhow::{Context, Result};
+use glib::Cast;
+use gst::prelude::*;
+use gst::{
+    BusSyncReply, Caps, ClockTime, Element, ElementFactory, FlowError, FlowSuccess, Fraction,
+    MessageView, Pipeline, SeekFlags, State,
+};
+use gst_app::{AppSink, AppSinkCallbacks};
+
+/// Height of the frames shown in the preview.
+const PREVIEW_HEIGHT: i32 = 360;
+
+pub enum PreviewEvent {
+    /// The frame at the position after opening or seeking.
+    Frame(PreviewFrame),
+    /// The video can't be played.
+    Error(String),
+}
+
+/// Frame in packed RGB.
+pub struct PreviewFrame {
+    /// Stream time of the frame.
+    pub pos: ClockTime,
+    pub duration: ClockTime,
+    pub frame: RawFrame,
+}
+
+/// Paused pipeline which sends the frame at each position seeked to.
+pub struct Preview {
+    pipeline: Pipeline,
+}
+
+impl Preview {
+    pub fn new(uri: &str, sender: glib::Sender<PreviewEvent>) -> Result<Self> {
+        let pipeline = Pipeline::new(None);
+        let decodebin = ElementFactory::make("uridecodebin", None)?;
+        let convert = ElementFactory::make("videoconvert", None)?;
+        let scale = ElementFactory::make("videoscale", None)?;
+        let sink = ElementFactory::make("appsink", None)?;
+        let sink = sink.dynamic_cast::<AppSink>().unwrap();
+        decodebin.set_property("uri", &uri)?;
+        let caps = Caps::builder("video/x-raw")
+            .field("format", &"RGB")
+            .field("height", &PREVIEW_HEIGHT)
+            .field("pixel-aspect-ratio", &Fraction::new(1, 1))
+            .build();
+        sink.set_caps(Some(&caps));
+        pipeline.add_many(&[&decodebin, &convert, &scale, sink.as_ref()])?;
+        Element::link_many(&[&convert, &scale, sink.as_ref()])?;
+
+        // Show the first video stream.
+        let convert_sink = convert.get_static_pad("sink").unwrap();
+        decodebin.connect_pad_added(move |_, pad| {
+            let is_video = pad
+                .get_current_caps()
+                .and_then(|caps| Some(caps.get_structure(0)?.get_name().starts_with("video/")))
+                .unwrap_or(false);
+            if is_video && !convert_sink.is_linked() {
+                let _ = pad.link(&convert_sink);
+            }
+        });
+        sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_preroll({
+                    let sender = sender.clone();
+                    move |sink| {
+                        let sample = sink.pull_preroll().map_err(|_| FlowError::Error)?;
+                        if let Some(frame) = PreviewFrame::from_sample(&sample) {
+                            let _ = sender.send(PreviewEvent::Frame(frame));
+                        }
+                        Ok(FlowSuccess::Ok)
+                    }
+                })
+                .build(),
+        );
+        pipeline.get_bus().unwrap().set_sync_handler(move |_, msg| {
+            if let MessageView::Error(err) = msg.view() {
+                let _ = sender.send(PreviewEvent::Error(err.get_error().to_string()));
+            }
+            BusSyncReply::Drop
+        });
+        pipeline
+            .set_state(State::Paused)
+            .context("failed to open video")?;
+        Ok(Preview { pipeline })
+    }
+
+    pub fn duration(&self) -> Option<ClockTime> {
+        self.pipeline
+            .query_duration::<ClockTime>()
+            .filter(|duration| duration.is_some())
+    }
+
+    /// Seek to the frame covering the position.
+    pub fn seek(&self, pos: ClockTime) -> Result<()> {
+        self.pipeline
+            .seek_simple(SeekFlags::FLUSH | SeekFlags::ACCURATE, pos)
+            .context("failed to seek")
+    }
+}
+
+impl Drop for Preview {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(State::Null);
+        self.pipeline.get_bus().unwrap().unset_sync_handler();
+    }
+}
+
+impl PreviewFrame {
+    fn from_sample(sample: &gst::Sample) -> Option<Self> {
+        let pos = sample_position(sample)?;
+        let duration = sample.get_buffer()?.get_duration();
+        let frame = RawFrame::from_sample(sample).ok()?;
+        Some(PreviewFrame {
+            pos,
+            duration,
+            frame,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use glib::Cast;
+use gst::tags::LanguageCode;
+use gst::{ClockTime, CoreError, Fraction, StreamError};
+use gst_pbutils::prelude::*;
+use gst_pbutils::{
+    pb_utils_get_codec_description, Discoverer, DiscovererInfo, DiscovererResult,
+    DiscovererVideoInfo,
+};
+use std::fmt::Write;
+
+/// Result of probing a file before it's queued.
+pub struct ProbeResult {
+    pub uri: Box<str>,
+    /// Information of the media, or the reason it can't be probed.
+    pub result: Result<MediaInfo, ProbeError>,
+}
+
+/// Reason a file can't be probed.
+pub enum ProbeError {
+    /// Plugins needed to play the file are missing. Sampling it still
+    /// reports which, so that they can be installed.
+    MissingPlugins(String),
+    Unplayable(String),
+}
+
+pub struct MediaInfo {
+    /// Duration, which is none if unknown, e.g. for live sources.
+    pub duration: ClockTime,
+    /// Whether the source is live, e.g. a stream or a capture device.
+    pub live: bool,
+    /// Whether the source can be seeked. Otherwise frames are captured as
+    /// it plays.
+    pub seekable: bool,
+    pub width: u32,
+    pub height: u32,
+    /// Frame rate, which is zero for variable frame rate.
+    pub framerate: Fraction,
+    pub video_codec: Option<String>,
+    pub audio_codecs: Vec<String>,
+    pub container: Option<String>,
+    /// Number of streams in the file.
+    pub streams: usize,
+    /// All the video streams, in the order the sampler indexes them.
+    pub video_streams: Vec<VideoStreamInfo>,
+}
+
+pub struct VideoStreamInfo {
+    pub width: u32,
+    pub height: u32,
+    pub language: Option<String>,
+    /// Whether the stream is a still picture, e.g. an attached cover.
+    pub still: bool,
+}
+
+impl VideoStreamInfo {
+    pub fn describe(&self) -> String {
+        let mut result = format!("{}×{}", self.width, self.height);
+        if let Some(language) = &self.language {
+            write!(result, " {}", language).unwrap();
+        }
+        if self.still {
+            result.push_str(" (still picture)");
+        }
+        result
+    }
+}
+
+impl MediaInfo {
+    /// A line of text summarizing the media for the file list.
+    pub fn summary(&self) -> String {
+        let mut result = String::new();
+        match self.duration.seconds() {
+            _ if self.live => result.push_str("live"),
+            Some(seconds) if seconds > 0 => write!(
+                result,
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )
+            .unwrap(),
+            _ => result.push_str("unknown duration"),
+        }
+        if !self.live && !self.seekable {
+            result.push_str(" (not seekable)");
+        }
+        write!(result, " · {}×{}", self.width, self.height).unwrap();
+        let (num, denom) = (*self.framerate.numer(), *self.framerate.denom());
+        if num > 0 && denom > 0 {
+            write!(result, " · {:.3} fps", num as f64 / denom as f64).unwrap();
+        }
+        let codecs = self
+            .video_codec
+            .iter()
+            .chain(self.audio_codecs.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !codecs.is_empty() {
+            write!(result, " · {}", codecs.join(" / ")).unwrap();
+        }
+        if let Some(container) = &self.container {
+            write!(result, " · {}", container).unwrap();
+        }
+        write!(result, " · {} streams", self.streams).unwrap();
+        result
+    }
+}
+
+pub fn probe(discoverer: &Discoverer, uri: &str) -> Result<MediaInfo, ProbeError> {
+    let info = match discoverer.discover_uri(uri) {
+        Ok(info) => info,
+        Err(e) => {
+            let reason = format!("failed to discover: {}", e);
+            let missing_plugins = e.kind::<CoreError>() == Some(CoreError::MissingPlugin)
+                || e.kind::<StreamError>() == Some(StreamError::CodecNotFound);
+            return Err(if missing_plugins {
+                ProbeError::MissingPlugins(reason)
+            } else {
+                ProbeError::Unplayable(reason)
+            });
+        }
+    };
+    // Plugins missing for other streams don't matter, as long as the video
+    // can be read.
+    media_info(&info).map_err(|e| {
+        let reason = format!("{:#}", e);
+        if info.get_result() == DiscovererResult::MissingPlugins {
+            ProbeError::MissingPlugins(reason)
+        } else {
+            ProbeError::Unplayable(reason)
+        }
+    })
+}
+
+fn media_info(info: &DiscovererInfo) -> Result<MediaInfo> {
+    let videos = info
+        .get_video_streams()
+        .into_iter()
+        .filter_map(|stream| stream.downcast::<DiscovererVideoInfo>().ok())
+        .collect::<Vec<_>>();
+    let video = videos
+        .iter()
+        .find(|video| !video.is_image())
+        .context("no video stream")?;
+
+    let describe = |caps: Option<gst::Caps>| -> Option<String> {
+        let caps = caps?;
+        let description = pb_utils_get_codec_description(&caps).ok()?;
+        Some(description.into())
+    };
+    Ok(MediaInfo {
+        duration: info.get_duration(),
+        live: info.get_live(),
+        seekable: info.get_seekable(),
+        width: video.get_width(),
+        height: video.get_height(),
+        framerate: video.get_framerate(),
+        video_codec: describe(video.get_caps()),
+        audio_codecs: info
+            .get_audio_streams()
+            .iter()
+            .filter_map(|audio| describe(audio.get_caps()))
+            .collect(),
+        container: info
+            .get_container_streams()
+            .first()
+            .and_then(|container| describe(container.get_caps())),
+        streams: info.get_stream_list().len(),
+        video_streams: videos
+            .iter()
+            .map(|video| VideoStreamInfo {
+                width: video.get_width(),
+                height: video.get_height(),
+                language: video
+                    .get_tags()
+                    .and_then(|tags| Some(tags.get::<LanguageCode>()?.get()?.to_owned())),
+                still: video.is_image(),
+            })
+            .collect(),
+    })
+}
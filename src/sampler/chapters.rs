@@ -0,0 +1,119 @@
+//! Sampling according to the chapters of a video.
+
+use super::TimeConstraints;
+use anyhow::Result;
+use gst::tags::Title;
+use gst::{ClockTime, Toc, TocEntry, TocEntryType};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How chapters affect where samples are taken.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "by", rename_all = "kebab-case")]
+pub enum ChapterSampling {
+    /// Take samples from the whole video regardless of chapters.
+    #[default]
+    Ignore,
+    /// Take the sample count from each chapter.
+    PerChapter,
+    /// Take the first frame of each chapter.
+    ChapterStart,
+}
+
+pub(super) struct Chapter {
+    pub start: ClockTime,
+    pub end: ClockTime,
+    pub title: Option<String>,
+}
+
+/// Collect the innermost chapters in the table of contents, in order.
+pub(super) fn chapters_from_toc(toc: &Toc, duration: ClockTime) -> Vec<Chapter> {
+    fn collect(entries: Vec<TocEntry>, chapters: &mut Vec<Chapter>) {
+        for entry in entries {
+            // Chapters with subchapters are covered by them, and sampling
+            // both would sample their parts twice.
+            let sub_entries = entry.get_sub_entries();
+            let has_subchapters = sub_entries
+                .iter()
+                .any(|sub_entry| sub_entry.get_entry_type() == TocEntryType::Chapter);
+            if entry.get_entry_type() == TocEntryType::Chapter && !has_subchapters {
+                // Chapters without times can't be sampled.
+                if let Some((start, stop)) = entry.get_start_stop_times() {
+                    let to_time = |time: i64| match time {
+                        time if time >= 0 => ClockTime::from_nseconds(time as u64),
+                        _ => ClockTime::none(),
+                    };
+                    chapters.push(Chapter {
+                        start: to_time(start),
+                        end: to_time(stop),
+                        title: entry
+                            .get_tags()
+                            .and_then(|tags| Some(tags.get::<Title>()?.get()?.to_owned())),
+                    });
+                }
+            }
+            // Editions contain chapters, and chapters may have subchapters.
+            collect(sub_entries, chapters);
+        }
+    }
+    let mut chapters = Vec::new();
+    collect(toc.get_entries(), &mut chapters);
+    chapters.retain(|chapter| chapter.start.is_some() && chapter.start < duration);
+    chapters.sort_by_key(|chapter| chapter.start);
+    // Chapters without an end last until the next one.
+    for i in 0..chapters.len() {
+        if chapters[i].end.is_none() || chapters[i].end > duration {
+            chapters[i].end = match chapters.get(i + 1) {
+                Some(next) => next.start,
+                None => duration,
+            };
+        }
+    }
+    chapters
+}
+
+/// Title of the chapter the position is in.
+pub(super) fn title_at(chapters: &[Chapter], pos: ClockTime) -> Option<&str> {
+    chapters
+        .iter()
+        .rev()
+        .find(|chapter| chapter.start <= pos && pos < chapter.end)
+        .and_then(|chapter| chapter.title.as_deref())
+}
+
+impl ChapterSampling {
+    /// Generate sorted sample offsets according to the chapters, within the
+    /// parts allowed by the constraints.
+    pub(super) fn sample_offsets(
+        &self,
+        chapters: &[Chapter],
+        constraints: &TimeConstraints,
+        duration: ClockTime,
+        samples: u32,
+        rng: &mut ThreadRng,
+    ) -> Result<Vec<ClockTime>> {
+        match self {
+            ChapterSampling::Ignore => constraints.sample_offsets(duration, samples, rng),
+            ChapterSampling::PerChapter => {
+                let mut offsets = Vec::new();
+                for chapter in chapters {
+                    // Chapters entirely left out by the constraints are
+                    // skipped.
+                    let range = (chapter.start, chapter.end);
+                    if let Ok(chapter_offsets) =
+                        constraints.sample_offsets_in(duration, range, samples, rng)
+                    {
+                        offsets.extend(chapter_offsets);
+                    }
+                }
+                offsets.sort();
+                Ok(offsets)
+            }
+            ChapterSampling::ChapterStart => Ok(chapters
+                .iter()
+                .map(|chapter| chapter.start)
+                .filter(|&start| constraints.allows(duration, start))
+                .collect()),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Colorimetry of video streams.
+
+use gst::prelude::*;
+use gst::{CapsRef, EventView, Pad, PadProbeData, PadProbeReturn, PadProbeType};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColorConfig {
+    /// Matrix to convert with instead of the one of the stream, for streams
+    /// with wrong or missing metadata.
+    pub matrix: Option<ColorMatrix>,
+    /// Range to convert with instead of the one of the stream.
+    pub range: Option<ColorRange>,
+    /// Whether to mark output images as sRGB.
+    pub srgb: bool,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        ColorConfig {
+            matrix: None,
+            range: None,
+            srgb: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMatrix {
+    Bt601,
+    Bt709,
+    Bt2020,
+    Smpte240m,
+    Fcc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorRange {
+    Full,
+    Limited,
+}
+
+/// Colorimetry in the values of the GstVideoColorRange,
+/// GstVideoColorMatrix, GstVideoTransferFunction and GstVideoColorPrimaries
+/// enums.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Colorimetry {
+    pub range: u32,
+    pub matrix: u32,
+    pub transfer: u32,
+    pub primaries: u32,
+}
+
+impl Colorimetry {
+    const fn new(range: u32, matrix: u32, transfer: u32, primaries: u32) -> Self {
+        Colorimetry {
+            range,
+            matrix,
+            transfer,
+            primaries,
+        }
+    }
+
+    /// Parse colorimetry in caps, which is either a name, or
+    /// `range:matrix:transfer:primaries` in numbers.
+    pub fn parse(colorimetry: &str) -> Option<Self> {
+        let named = match colorimetry {
+            "bt601" => Some(Colorimetry::new(2, 4, 5, 4)),
+            "bt709" => Some(Colorimetry::new(2, 3, 5, 1)),
+            "smpte240m" => Some(Colorimetry::new(2, 5, 6, 5)),
+            "sRGB" => Some(Colorimetry::new(1, 1, 7, 1)),
+            "bt2020" => Some(Colorimetry::new(2, 6, 11, 7)),
+            "bt2020-10" => Some(Colorimetry::new(2, 6, 13, 7)),
+            "bt2100-pq" => Some(Colorimetry::new(2, 6, 14, 7)),
+            "bt2100-hlg" => Some(Colorimetry::new(2, 6, 15, 7)),
+            _ => None,
+        };
+        if named.is_some() {
+            return named;
+        }
+        let mut values = colorimetry.split(':').map(|value| value.parse().ok());
+        let mut next = || values.next().flatten();
+        Some(Colorimetry::new(next()?, next()?, next()?, next()?))
+    }
+
+    /// Colorimetry GStreamer assumes for YUV video without any.
+    fn default_for_height(height: i32) -> Self {
+        if height > 576 {
+            Colorimetry::new(2, 3, 5, 1)
+        } else {
+            Colorimetry::new(2, 4, 5, 4)
+        }
+    }
+
+    fn with_overrides(mut self, config: &ColorConfig) -> Self {
+        if let Some(matrix) = config.matrix {
+            self.matrix = match matrix {
+                ColorMatrix::Fcc => 2,
+                ColorMatrix::Bt709 => 3,
+                ColorMatrix::Bt601 => 4,
+                ColorMatrix::Smpte240m => 5,
+                ColorMatrix::Bt2020 => 6,
+            };
+        }
+        if let Some(range) = config.range {
+            self.range = match range {
+                ColorRange::Full => 1,
+                ColorRange::Limited => 2,
+            };
+        }
+        self
+    }
+}
+
+impl fmt::Display for Colorimetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.range, self.matrix, self.transfer, self.primaries
+        )
+    }
+}
+
+/// Rewrite the colorimetry in the caps from the pad as configured.
+///
+/// The probe stays until the pad is gone, which decodebin does when the
+/// pipeline is stopped.
+pub(super) fn override_colorimetry(pad: &Pad, config: &ColorConfig) {
+    if config.matrix.is_none() && config.range.is_none() {
+        return;
+    }
+    let config = *config;
+    pad.add_probe(PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        if let Some(PadProbeData::Event(event)) = &mut info.data {
+            let caps = match event.view() {
+                EventView::Caps(caps) => Some(caps.get_caps_owned()),
+                _ => None,
+            };
+            if let Some(mut caps) = caps {
+                override_caps(caps.make_mut(), &config);
+                *event = gst::event::Caps::new(&caps);
+            }
+        }
+        PadProbeReturn::Ok
+    });
+}
+
+fn override_caps(caps: &mut CapsRef, config: &ColorConfig) {
+    let s = match caps.get_mut_structure(0) {
+        Some(s) => s,
+        None => return,
+    };
+    let colorimetry = s
+        .get::<String>("colorimetry")
+        .ok()
+        .flatten()
+        .and_then(|colorimetry| Colorimetry::parse(&colorimetry));
+    let colorimetry = match colorimetry {
+        Some(colorimetry) => colorimetry,
+        None => {
+            let height = s.get_some::<i32>("height").unwrap_or_default();
+            Colorimetry::default_for_height(height)
+        }
+    };
+    let colorimetry = colorimetry.with_overrides(config).to_string();
+    s.set("colorimetry", &colorimetry);
+}
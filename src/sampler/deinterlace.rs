@@ -0,0 +1,47 @@
+//! Deinterlacing of interlaced sources.
+
+use super::set_enum_property;
+use anyhow::{Context, Result};
+use gst::prelude::*;
+use gst::Element;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeinterlaceConfig {
+    pub mode: DeinterlaceMode,
+    /// Method of the deinterlace element, e.g. `linear` or `greedyh`, or
+    /// none for its default.
+    pub method: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeinterlaceMode {
+    /// Deinterlace streams which are marked interlaced in their caps.
+    #[default]
+    Auto,
+    /// Deinterlace all streams, for interlaced streams marked wrongly.
+    On,
+    Off,
+}
+
+/// Configure the deinterlace element for a task.
+///
+/// `forced` tells it to deinterlace regardless of what the caps say.
+pub(super) fn configure(element: &Element, config: &DeinterlaceConfig, forced: bool) -> Result<()> {
+    let mode = if forced { "interlaced" } else { "auto" };
+    set_enum_property(element, "mode", mode)?;
+    match &config.method {
+        Some(method) => set_enum_property(element, "method", method)?,
+        // The element is reused, so undo the method of earlier tasks.
+        None => {
+            let default = element
+                .find_property("method")
+                .and_then(|pspec| pspec.get_default_value())
+                .context("no default deinterlace method")?;
+            element.set_property("method", &default)?;
+        }
+    }
+    Ok(())
+}
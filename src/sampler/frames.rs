@@ -0,0 +1,118 @@
+//! Conversion between frame numbers and times.
+
+use gst::{ClockTime, Fraction};
+
+/// Duration of a frame, as a fraction of nanoseconds.
+pub(super) struct FrameRate {
+    frame_numer: u128,
+    frame_denom: u128,
+    /// Whether the stream has a constant frame rate. Otherwise, the rate is
+    /// the average one and the conversions are estimates.
+    exact: bool,
+}
+
+impl FrameRate {
+    /// Use the frame rate from the caps, or the average rate from the
+    /// number of frames for variable frame rate streams.
+    pub fn new(
+        framerate: Option<Fraction>,
+        frames: Option<u64>,
+        duration: ClockTime,
+    ) -> Option<Self> {
+        if let Some(framerate) = framerate {
+            return Some(FrameRate {
+                frame_numer: *framerate.denom() as u128 * 1_000_000_000,
+                frame_denom: *framerate.numer() as u128,
+                exact: true,
+            });
+        }
+        let frames = frames.filter(|&frames| frames > 0)?;
+        Some(FrameRate {
+            frame_numer: duration.nseconds()? as u128,
+            frame_denom: frames as u128,
+            exact: false,
+        })
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    /// Time in the middle of the frame, which accurate seeks land on
+    /// despite rounding of timestamps.
+    pub fn time_of(&self, frame: u64) -> ClockTime {
+        let ns = (frame as u128 * 2 + 1) * self.frame_numer / (self.frame_denom * 2);
+        ClockTime::from_nseconds(ns as u64)
+    }
+
+    /// Number of the frame starting at the position.
+    pub fn frame_at(&self, pos: ClockTime) -> Option<u64> {
+        let ns = pos.nseconds()? as u128;
+        // Round to the nearest since timestamps are rounded.
+        let frame = (ns * self.frame_denom * 2 + self.frame_numer) / (self.frame_numer * 2);
+        Some(frame as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    /// Start of the frame at an exact rate of `numer / denom`, which is
+    /// what timestamps of frames are rounded from.
+    fn frame_start(frame: u64, numer: u64, denom: u64) -> u64 {
+        frame * denom * SECOND / numer
+    }
+
+    #[test]
+    fn converts_at_exact_rate() {
+        let rate =
+            FrameRate::new(Some(Fraction::new(30000, 1001)), None, ClockTime::none()).unwrap();
+        assert!(rate.is_exact());
+        assert_eq!(rate.time_of(0), ClockTime::from_nseconds(16_683_333));
+        assert_eq!(rate.time_of(30), ClockTime::from_nseconds(1_017_683_333));
+        assert_eq!(
+            rate.frame_at(ClockTime::from_nseconds(1_001_000_000)),
+            Some(30)
+        );
+        // Timestamps are rounded down to nanoseconds.
+        assert_eq!(
+            rate.frame_at(ClockTime::from_nseconds(1_000_999_999)),
+            Some(30)
+        );
+        assert_eq!(rate.frame_at(ClockTime::none()), None);
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        for &(numer, denom) in &[(30000, 1001), (25, 1), (24000, 1001), (60, 1)] {
+            let rate = FrameRate::new(
+                Some(Fraction::new(numer as i32, denom as i32)),
+                None,
+                ClockTime::none(),
+            )
+            .unwrap();
+            for frame in 0..10_000 {
+                let start = frame_start(frame, numer, denom);
+                let end = frame_start(frame + 1, numer, denom);
+                let time = rate.time_of(frame).nseconds().unwrap();
+                assert!(start <= time && time < end, "frame {} at {}", frame, time);
+                let frame_at = rate.frame_at(ClockTime::from_nseconds(start));
+                assert_eq!(frame_at, Some(frame), "frame at {}", start);
+            }
+        }
+    }
+
+    #[test]
+    fn estimates_variable_frame_rate() {
+        let duration = ClockTime::from_seconds(4);
+        let rate = FrameRate::new(None, Some(100), duration).unwrap();
+        assert!(!rate.is_exact());
+        assert_eq!(rate.time_of(10), ClockTime::from_mseconds(420));
+        assert_eq!(rate.frame_at(ClockTime::from_mseconds(400)), Some(10));
+        assert!(FrameRate::new(None, Some(0), duration).is_none());
+        assert!(FrameRate::new(None, None, duration).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Tone mapping of HDR frames to SDR.
+//!
+//! videoconvert doesn't convert transfer functions, so HDR frames are taken
+//! from it in 16-bit RGB still encoded with the HDR transfer function and
+//! BT.2020 primaries, and converted here.
+
+use super::color::Colorimetry;
+use super::output::{Image, OutputFormat, RawFrame};
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+/// How to handle HDR sources.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HdrConfig {
+    /// Whether to tone map HDR frames, rather than leave them washed-out.
+    pub tone_map: bool,
+    /// Peak luminance in nits assumed for the content.
+    pub peak: f32,
+}
+
+impl Default for HdrConfig {
+    fn default() -> Self {
+        HdrConfig {
+            tone_map: true,
+            peak: 1000.,
+        }
+    }
+}
+
+/// HDR transfer function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Transfer {
+    /// SMPTE ST 2084, used by HDR10.
+    Pq,
+    /// ARIB STD-B67, i.e. hybrid log-gamma.
+    Hlg,
+}
+
+/// Luminance in nits of SDR reference white, as in ITU-R BT.2408.
+const REFERENCE_WHITE: f32 = 203.;
+
+impl Transfer {
+    /// Find the HDR transfer function from the colorimetry in caps.
+    pub fn from_colorimetry(colorimetry: &str) -> Option<Self> {
+        match Colorimetry::parse(colorimetry)?.transfer {
+            14 => Some(Transfer::Pq),
+            15 => Some(Transfer::Hlg),
+            _ => None,
+        }
+    }
+
+    /// Convert a non-linear signal value to display light in nits.
+    fn to_nits(self, value: f32, peak: f32) -> f32 {
+        match self {
+            Transfer::Pq => {
+                const M1: f32 = 2610. / 16384.;
+                const M2: f32 = 2523. / 4096. * 128.;
+                const C1: f32 = 3424. / 4096.;
+                const C2: f32 = 2413. / 4096. * 32.;
+                const C3: f32 = 2392. / 4096. * 32.;
+                let p = value.powf(1. / M2);
+                ((p - C1).max(0.) / (C2 - C3 * p)).powf(1. / M1) * 10000.
+            }
+            Transfer::Hlg => {
+                const A: f32 = 0.178_832_77;
+                const B: f32 = 0.284_668_92;
+                const C: f32 = 0.559_910_7;
+                let scene = if value <= 0.5 {
+                    value * value / 3.
+                } else {
+                    (((value - C) / A).exp() + B) / 12.
+                };
+                // The system gamma is applied on luminance in `tone_map`.
+                scene * peak
+            }
+        }
+    }
+}
+
+/// Convert an HDR frame in ARGB64 to an SDR image in the output format.
+pub(super) fn tone_map(
+    frame: &RawFrame,
+    transfer: Transfer,
+    config: &HdrConfig,
+    format: OutputFormat,
+) -> Result<Image> {
+    ensure!(
+        frame.format == "ARGB64",
+        "unexpected format {}",
+        frame.format
+    );
+    let lut = (0..=u16::MAX)
+        .map(|value| transfer.to_nits(value as f32 / u16::MAX as f32, config.peak))
+        .collect::<Vec<_>>();
+    let white = config.peak / REFERENCE_WHITE;
+    let samples = frame.samples16().collect::<Vec<_>>();
+    let pixels = samples.chunks_exact(4).map(|pixel| {
+        let [r, g, b] = [
+            lut[pixel[1] as usize],
+            lut[pixel[2] as usize],
+            lut[pixel[3] as usize],
+        ];
+        // Convert BT.2020 primaries to BT.709.
+        let rgb = [
+            1.6605 * r - 0.5876 * g - 0.0728 * b,
+            -0.1246 * r + 1.1329 * g - 0.0083 * b,
+            -0.0182 * r - 0.1006 * g + 1.1187 * b,
+        ];
+        let luminance = 0.2627 * r + 0.6780 * g + 0.0593 * b;
+        let mut scale = 1. / REFERENCE_WHITE;
+        if transfer == Transfer::Hlg && luminance > 0. {
+            // HLG system gamma of 1.2 for the nominal 1000 nits display.
+            scale *= (luminance / config.peak).powf(0.2);
+        }
+        // Extended Reinhard on luminance, which keeps the hue.
+        let l = luminance * scale;
+        if l > 0. {
+            scale *= (1. + l / (white * white)) / (1. + l);
+        }
+        let rgb = rgb.map(|channel| srgb_oetf((channel * scale).clamp(0., 1.)));
+        (rgb, pixel[0] as f32 / u16::MAX as f32)
+    });
+    Ok(Image::from_pixels(
+        frame.width,
+        frame.height,
+        format,
+        pixels,
+    ))
+}
+
+fn srgb_oetf(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
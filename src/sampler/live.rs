@@ -0,0 +1,110 @@
+//! Sampling from sources which can't be seeked, e.g. live streams and
+//! capture devices, by capturing frames as they play.
+
+use super::stream::VideoPad;
+use super::{pull_frame, TaskBus};
+use anyhow::Result;
+use glib::{ObjectExt, SignalHandlerId};
+use gst::prelude::*;
+use gst::{ClockTime, Element, Sample};
+use gst_app::AppSink;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How to capture samples from sources which can't be seeked.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LiveCapture {
+    /// Length of the capture window in seconds.
+    pub window: u64,
+    /// Whether to capture at random times in the window, rather than at
+    /// fixed intervals.
+    pub random: bool,
+}
+
+impl Default for LiveCapture {
+    fn default() -> Self {
+        LiveCapture {
+            window: 60,
+            random: false,
+        }
+    }
+}
+
+/// Link the first video stream decodebin exposes to `head`, until the
+/// returned guard is dropped.
+///
+/// Live sources don't produce any data before playing, so their streams
+/// can't be chosen upfront like other sources.
+pub(super) fn link_on_pad_added(decodebin: &Element, head: &Element) -> PadAddedGuard {
+    let sink_pad = head.get_static_pad("sink").unwrap();
+    let handler = decodebin.connect_pad_added(move |_, pad| {
+        if sink_pad.is_linked() {
+            return;
+        }
+        match VideoPad::new(pad.clone()) {
+            Some(video) if video.is_decoded() && !video.is_still() => {
+                // Another stream may have won the race to link.
+                let _ = pad.link(&sink_pad);
+            }
+            _ => {}
+        }
+    });
+    PadAddedGuard {
+        element: decodebin.clone(),
+        handler: Some(handler),
+    }
+}
+
+pub(super) struct PadAddedGuard {
+    element: Element,
+    handler: Option<SignalHandlerId>,
+}
+
+impl Drop for PadAddedGuard {
+    fn drop(&mut self) {
+        self.element.disconnect(self.handler.take().unwrap());
+    }
+}
+
+/// Capture samples from a playing pipeline at wall-clock offsets in the
+/// capture window, which starts when the first frame arrives.
+///
+/// The appsink should be set to drop old buffers, so that the frame pulled
+/// is the latest one.
+pub(super) fn capture<F>(
+    sink: &AppSink,
+    bus: &mut TaskBus,
+    capture: &LiveCapture,
+    samples: u32,
+    rng: &mut ThreadRng,
+    timeout: Duration,
+    mut handle_sample: F,
+) -> Result<()>
+where
+    F: FnMut(usize, ClockTime, Sample) -> Result<()>,
+{
+    let window = Duration::from_secs(capture.window);
+    let mut offsets = (0..samples)
+        .map(|i| {
+            if capture.random {
+                window.mul_f64(rng.gen())
+            } else {
+                window * i / samples
+            }
+        })
+        .collect::<Vec<_>>();
+    offsets.sort();
+
+    let pull_sample = |timeout| sink.try_pull_sample(timeout);
+    pull_frame(bus, timeout, pull_sample)?;
+    let start = Instant::now();
+    for (i, offset) in offsets.into_iter().enumerate() {
+        bus.idle_until(start + offset)?;
+        let sample = pull_frame(bus, timeout, pull_sample)?;
+        handle_sample(i, offset.into(), sample)?;
+        bus.drain()?;
+    }
+    Ok(())
+}
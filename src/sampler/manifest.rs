@@ -0,0 +1,206 @@
+//! Manifest listing the samples of a task along with their metadata.
+
+use super::{drift, SampleMeta};
+use anyhow::{Context, Result};
+use gst::ClockTime;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// CSV file with a row for each sample written.
+pub(super) struct Manifest {
+    writer: BufWriter<File>,
+}
+
+pub(super) struct ManifestRow<'a> {
+    pub file: &'a str,
+    /// Position the sample was requested at.
+    pub requested: ClockTime,
+    pub meta: &'a SampleMeta<'a>,
+}
+
+impl Manifest {
+    /// Create the manifest, or append to it if `append` is set.
+    pub fn open(path: &Path, append: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .context("failed to create manifest")?;
+        let empty = file.metadata().map_or(true, |metadata| metadata.len() == 0);
+        let mut manifest = Manifest {
+            writer: BufWriter::new(file),
+        };
+        if empty {
+            manifest.write_record(&[
+                "file",
+                "requested",
+                "position",
+                "drift",
+                "frame",
+                "chapter",
+                "subtitle",
+            ])?;
+        }
+        Ok(manifest)
+    }
+
+    pub fn write_row(&mut self, row: &ManifestRow) -> Result<()> {
+        let meta = row.meta;
+        let frame = meta.frame.map(|frame| frame.to_string());
+        self.write_record(&[
+            row.file,
+            &format_seconds(row.requested),
+            &format_seconds(meta.pos),
+            &format_drift(drift(row.requested, meta.pos)),
+            frame.as_deref().unwrap_or_default(),
+            meta.chapter.unwrap_or_default(),
+            meta.subtitle.unwrap_or_default(),
+        ])?;
+        // Keep the manifest complete for the samples written so far, in
+        // case the task fails later.
+        self.writer.flush().context("failed to write manifest")
+    }
+
+    fn write_record(&mut self, fields: &[&str]) -> Result<()> {
+        let record = fields
+            .iter()
+            .map(|field| escape(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(self.writer, "{}", record).context("failed to write manifest")
+    }
+}
+
+fn format_seconds(time: ClockTime) -> String {
+    match time.nseconds() {
+        Some(ns) => format!("{}.{:09}", ns / 1_000_000_000, ns % 1_000_000_000),
+        None => String::new(),
+    }
+}
+
+fn format_drift(drift: i64) -> String {
+    let sign = if drift < 0 { "-" } else { "" };
+    let drift = drift.unsigned_abs();
+    format!(
+        "{}{}.{:09}",
+        sign,
+        drift / 1_000_000_000,
+        drift % 1_000_000_000
+    )
+}
+
+/// Remove the rows of the given files from the manifest.
+pub(super) fn remove_rows(path: &Path, files: &[&str]) -> Result<()> {
+    let text = fs::read_to_string(path).context("failed to read manifest")?;
+    let mut result = String::new();
+    for (i, record) in split_records(&text).into_iter().enumerate() {
+        // Keep the header regardless.
+        if i > 0 && files.contains(&unescape(first_field(record)).as_str()) {
+            continue;
+        }
+        result.push_str(record);
+        result.push('\n');
+    }
+    fs::write(path, result).context("failed to write manifest")
+}
+
+/// Split the text into records, without the line breaks between them.
+fn split_records(text: &str) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                records.push(text[start..i].trim_end_matches('\r'));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < text.len() {
+        records.push(&text[start..]);
+    }
+    records
+}
+
+fn first_field(record: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in record.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => return &record[..i],
+            _ => {}
+        }
+    }
+    record
+}
+
+fn unescape(field: &str) -> String {
+    match field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+    {
+        Some(field) => field.replace("\"\"", "\""),
+        None => field.to_owned(),
+    }
+}
+
+/// Quote the field if needed, as in RFC 4180.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn escapes_fields() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        for field in &["plain", "a,b", "say \"hi\"", "two\nlines"] {
+            assert_eq!(unescape(&escape(field)), *field);
+        }
+    }
+
+    #[test]
+    fn splits_records_with_quoted_line_breaks() {
+        let text = "file,subtitle\r\na.png,\"one\ntwo\"\nb.png,\n";
+        assert_eq!(
+            split_records(text),
+            vec!["file,subtitle", "a.png,\"one\ntwo\"", "b.png,"],
+        );
+    }
+
+    #[test]
+    fn finds_quoted_first_field() {
+        assert_eq!(first_field("\"a,b.png\",1"), "\"a,b.png\"");
+        assert_eq!(first_field("a.png"), "a.png");
+    }
+
+    #[test]
+    fn removes_rows_of_files() {
+        let path = env::temp_dir().join(format!("video-sampler-manifest-{}.csv", process::id()));
+        fs::write(
+            &path,
+            "file,subtitle\na.png,\"one\ntwo\"\n\"b,c.png\",\nd.png,x\n",
+        )
+        .unwrap();
+        let result = remove_rows(&path, &["file", "a.png", "b,c.png"]);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(text, "file,subtitle\nd.png,x\n");
+    }
+}
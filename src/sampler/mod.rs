@@ -0,0 +1,1104 @@
+use crate::plugins::{MissingPlugin, MissingPlugins};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use glib::{Cast, EnumClass};
+use gst::prelude::*;
+use gst::{
+    Bus, Caps, ClockTime, Element, ElementFactory, EventType, EventView, Format, Fraction, Message,
+    MessageView, Object, Pipeline, QueryView, Sample, SeekFlags, State, StateChangeSuccess, Toc,
+};
+use gst_app::AppSink;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub use self::chapters::ChapterSampling;
+pub use self::color::ColorConfig;
+pub use self::deinterlace::{DeinterlaceConfig, DeinterlaceMode};
+use self::frames::FrameRate;
+pub use self::hdr::HdrConfig;
+use self::hdr::Transfer;
+pub use self::live::LiveCapture;
+use self::manifest::{Manifest, ManifestRow};
+use self::output::Image;
+pub use self::output::OutputFormat;
+pub use self::output::RawFrame;
+pub use self::overlay::OverlayConfig;
+pub use self::range::TimeConstraints;
+pub use self::stream::StreamSelection;
+use self::stream::VideoPad;
+pub use self::subtitles::SubtitleConfig;
+pub use self::timestamps::{format_timestamp, parse_timestamp_file, parse_timestamps, Timestamp};
+
+mod chapters;
+mod color;
+mod deinterlace;
+mod frames;
+mod hdr;
+mod live;
+mod manifest;
+mod output;
+mod overlay;
+mod range;
+mod stream;
+mod subtitles;
+mod timestamps;
+
+/// Timeouts, in seconds, after which a task is considered hung.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SamplerConfig {
+    /// Time allowed for the source to be opened and its streams found.
+    state_change_timeout: u64,
+    /// Time allowed for the first frame to be ready after linking.
+    preroll_timeout: u64,
+    /// Time allowed for each seek to deliver its frame.
+    seek_timeout: u64,
+    /// Time allowed for probing a file before it's queued.
+    probe_timeout: u64,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        SamplerConfig {
+            state_change_timeout: 10,
+            preroll_timeout: 30,
+            seek_timeout: 30,
+            probe_timeout: 10,
+        }
+    }
+}
+
+impl SamplerConfig {
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_secs(self.probe_timeout)
+    }
+}
+
+/// How to seek to the sample positions.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "by", rename_all = "kebab-case")]
+pub enum SeekMode {
+    /// Decode up to the exact position, which is slow on files with long
+    /// distances between keyframes.
+    #[default]
+    Accurate,
+    /// Take the keyframe nearest to the position, which is fast but may be
+    /// far from it.
+    Keyframe,
+}
+
+impl SeekMode {
+    fn flags(&self) -> SeekFlags {
+        match self {
+            SeekMode::Accurate => SeekFlags::FLUSH | SeekFlags::ACCURATE,
+            SeekMode::Keyframe => SeekFlags::FLUSH | SeekFlags::KEY_UNIT | SeekFlags::SNAP_NEAREST,
+        }
+    }
+}
+
+pub struct TaskContext {
+    pub rng: ThreadRng,
+    config: SamplerConfig,
+    /// Pipeline kept from previous tasks run in this context.
+    pipeline: Option<SamplerPipeline>,
+}
+
+impl TaskContext {
+    pub fn new(config: SamplerConfig) -> Self {
+        TaskContext {
+            rng: rand::thread_rng(),
+            config,
+            pipeline: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Task {
+    pub prefix: Arc<str>,
+    pub height: u32,
+    pub samples: u32,
+    pub target: Arc<Path>,
+    pub index: u32,
+    /// URI of the video to sample.
+    pub source: Box<str>,
+    pub stream: StreamSelection,
+    /// How to sample if the source turns out to be live or not seekable.
+    pub live: LiveCapture,
+    /// Parts of the video to take samples from.
+    pub constraints: TimeConstraints,
+    /// Timestamps to sample at instead of random ones.
+    pub timestamps: Option<Vec<Timestamp>>,
+    /// How to take samples according to the chapters of the video.
+    pub chapters: ChapterSampling,
+    /// Template of the output file names, see `file_name`.
+    pub name_template: Arc<str>,
+    pub seek_mode: SeekMode,
+    /// Whether to write a manifest of the samples next to them.
+    pub manifest: bool,
+    /// Pixel format of the output images.
+    pub format: OutputFormat,
+    pub hdr: HdrConfig,
+    pub deinterlace: DeinterlaceConfig,
+    pub color: ColorConfig,
+    pub overlay: OverlayConfig,
+    /// Name of the video to show in overlays.
+    pub display_name: Box<str>,
+    /// Subtitles to render or list, which are only read from seekable
+    /// sources.
+    pub subtitles: SubtitleConfig,
+    /// Whether the task takes replacements for samples rejected from an
+    /// earlier task, whose manifest is appended to.
+    pub replacing: bool,
+    /// Task reference for sending progress.
+    pub ref_idx: u32,
+}
+
+impl Task {
+    fn manifest_path(&self) -> PathBuf {
+        let file_name = format!("{}-{}-manifest.csv", self.prefix, self.index);
+        self.target.join(file_name)
+    }
+}
+
+pub struct TaskResult {
+    /// Warnings reported by the pipeline during the task.
+    pub warnings: Vec<String>,
+}
+
+/// Run the task, reporting the fraction done, and the path of each sample
+/// once it's written.
+pub fn run_task<P, S>(
+    ctx: &mut TaskContext,
+    task: Task,
+    report_progress: P,
+    report_sample: S,
+) -> Result<TaskResult>
+where
+    P: Fn(f64),
+    S: Fn(&Path),
+{
+    if ctx.pipeline.is_none() {
+        ctx.pipeline = Some(SamplerPipeline::new()?);
+    }
+    let result = run_task_with_pipeline(
+        ctx.pipeline.as_ref().unwrap(),
+        &ctx.config,
+        &mut ctx.rng,
+        task,
+        report_progress,
+        report_sample,
+    );
+    // Don't trust a pipeline which has failed, and build a fresh one for
+    // the next task instead.
+    if result.is_err() {
+        ctx.pipeline = None;
+    }
+    result
+}
+
+/// Elements of the sampling pipeline.
+///
+/// Building a pipeline is relatively expensive compared to sampling a
+/// short video, so a worker keeps its pipeline around and only swaps the
+/// source URI and sink caps for each task.
+struct SamplerPipeline {
+    pipeline: Pipeline,
+    /// A uridecodebin, which picks the source element for the URI.
+    decodebin: Element,
+    /// Deinterlace element, if available, which is linked before convert
+    /// only for the tasks which need it.
+    deinterlace: Option<Element>,
+    convert: Element,
+    scale: Element,
+    sink: AppSink,
+}
+
+impl SamplerPipeline {
+    fn new() -> Result<Self> {
+        let pipeline = Pipeline::new(None);
+        let sink = ElementFactory::make("appsink", None)?;
+        let sink = sink.dynamic_cast::<AppSink>().unwrap();
+        sink.set_max_buffers(1);
+        let decodebin = ElementFactory::make("uridecodebin", None)?;
+        let convert = ElementFactory::make("videoconvert", None)?;
+        let scale = ElementFactory::make("videoscale", None)?;
+        // Deinterlacing is optional, so a missing plugin shouldn't fail
+        // every task.
+        let deinterlace = ElementFactory::make("deinterlace", None).ok();
+
+        pipeline.add_many(&[&decodebin, &convert, &scale, sink.as_ref()])?;
+        if let Some(deinterlace) = &deinterlace {
+            pipeline.add(deinterlace)?;
+        }
+        Element::link_many(&[&convert, &scale, sink.as_ref()])?;
+
+        Ok(SamplerPipeline {
+            pipeline,
+            decodebin,
+            deinterlace,
+            convert,
+            scale,
+            sink,
+        })
+    }
+}
+
+fn run_task_with_pipeline<P, S>(
+    pipeline: &SamplerPipeline,
+    config: &SamplerConfig,
+    rng: &mut ThreadRng,
+    task: Task,
+    report_progress: P,
+    report_sample: S,
+) -> Result<TaskResult>
+where
+    P: Fn(f64),
+    S: Fn(&Path),
+{
+    let SamplerPipeline {
+        pipeline,
+        decodebin,
+        deinterlace,
+        convert,
+        scale,
+        sink,
+    } = pipeline;
+    // The pipeline is always in null state between tasks, and decodebin
+    // would have dropped its pads from the previous source by then.
+    decodebin.set_property("uri", &&*task.source)?;
+    sink.set_drop(false);
+
+    let overlays = overlay::insert(
+        pipeline,
+        scale,
+        sink.as_ref(),
+        &task.overlay,
+        &task.display_name,
+        task.subtitles.render,
+    )?;
+    // Before we change the state, ensure we reset it when we return.
+    // This is important when we return from error path.
+    let _auto_reset_pipeline = AutoStateReset(pipeline.clone());
+    let mut bus = TaskBus::new(pipeline.get_bus().unwrap());
+    let state_change = bus
+        .set_state(pipeline, State::Paused)
+        .context("failed to set pipeline state to paused")?;
+
+    let mut manifest = if task.manifest {
+        Some(Manifest::open(&task.manifest_path(), task.replacing)?)
+    } else {
+        None
+    };
+    // Samples captured as the source plays, rather than seeked to.
+    let mut handle_sample =
+        |output: &FrameOutput, i: usize, offset: ClockTime, sample: Sample| -> Result<()> {
+            let meta = SampleMeta {
+                pos: sample_position(&sample).unwrap_or(offset),
+                frame: None,
+                chapter: None,
+                subtitle: None,
+            };
+            let file = write_sample(&task, output, &meta, &sample)?;
+            if let Some(manifest) = &mut manifest {
+                manifest.write_row(&ManifestRow {
+                    file: &file,
+                    requested: offset,
+                    meta: &meta,
+                })?;
+            }
+            report_sample(&task.target.join(&file));
+            report_progress((i + 1) as f64 / task.samples as f64);
+            Ok(())
+        };
+    let preroll_timeout = Duration::from_secs(config.preroll_timeout);
+
+    if state_change == StateChangeSuccess::NoPreroll {
+        ensure!(
+            task.timestamps.is_none(),
+            "can't sample at timestamps from a live source"
+        );
+        // Live sources only expose their streams once playing, so link
+        // the stream when it shows up, and let videoscale pick the width.
+        // Live sources are often interlaced, and there are no caps to check
+        // yet, so let the deinterlace element check them.
+        let head = video_head(
+            convert,
+            deinterlace.as_ref(),
+            &task.deinterlace,
+            task.deinterlace.mode != DeinterlaceMode::Off,
+        )?;
+        let _link_guard = live::link_on_pad_added(decodebin, head);
+        let caps = Caps::builder("video/x-raw")
+            .field("format", &task.format.caps_format())
+            .field("height", &(task.height as i32))
+            .field("pixel-aspect-ratio", &Fraction::new(1, 1))
+            .build();
+        sink.set_caps(Some(&caps));
+        sink.set_drop(true);
+        bus.set_state(pipeline, State::Playing)
+            .context("failed to set pipeline state to playing")?;
+        let output = FrameOutput {
+            hdr: None,
+            srgb: task.color.srgb,
+        };
+        live::capture(
+            sink,
+            &mut bus,
+            &task.live,
+            task.samples,
+            rng,
+            preroll_timeout,
+            |i, pos, sample| handle_sample(&output, i, pos, sample),
+        )?;
+        return Ok(TaskResult {
+            warnings: bus.warnings,
+        });
+    }
+
+    let timeout = Duration::from_secs(config.state_change_timeout);
+    bus.wait_for_state_change_to(decodebin.as_ref(), State::Paused, timeout)
+        .context("failed to open source")?;
+
+    // Choose the video stream to sample.
+    let video_pads = decodebin
+        .get_src_pads()
+        .into_iter()
+        .filter_map(VideoPad::new)
+        .collect::<Vec<_>>();
+    if video_pads.is_empty() {
+        // The video stream may have been dropped for lack of a decoder.
+        bus.drain()?;
+        bus.check_missing_plugins()?;
+        bail!("no video stream found");
+    }
+    let video_pad = task
+        .stream
+        .select(&video_pads)
+        .with_context(|| format!("no video stream matching {:?}", task.stream))?;
+    if !video_pad.is_decoded() {
+        // Decodebin reports the missing decoder on the bus.
+        bus.drain()?;
+        bus.check_missing_plugins()?;
+        bail!("no decoder for the video stream");
+    }
+
+    // Setup the sink to accept the data we want. HDR frames are tone
+    // mapped from 16-bit data.
+    let transfer = video_pad
+        .colorimetry
+        .as_deref()
+        .and_then(Transfer::from_colorimetry);
+    let output = FrameOutput {
+        hdr: transfer.filter(|_| task.hdr.tone_map),
+        // HDR frames which aren't tone mapped aren't sRGB.
+        srgb: task.color.srgb && (transfer.is_none() || task.hdr.tone_map),
+    };
+    let format = match output.hdr {
+        Some(_) => "ARGB64",
+        None => task.format.caps_format(),
+    };
+    let height = task.height as i32;
+    let width = video_pad.width * height / video_pad.height;
+    let caps = Caps::builder("video/x-raw")
+        .field("format", &format)
+        .field("width", &width)
+        .field("height", &height)
+        .build();
+    sink.set_caps(Some(&caps));
+
+    // Connect the video handling side of pipeline on to the chosen stream.
+    // Other streams are left unlinked, which decodebin is fine with.
+    let deinterlacing = match task.deinterlace.mode {
+        DeinterlaceMode::Auto => video_pad.interlaced,
+        DeinterlaceMode::On => true,
+        DeinterlaceMode::Off => false,
+    };
+    let head = video_head(
+        convert,
+        deinterlace.as_ref(),
+        &task.deinterlace,
+        deinterlacing,
+    )?;
+    color::override_colorimetry(&video_pad.pad, &task.color);
+    let head_sink = head.get_static_pad("sink").unwrap();
+    video_pad
+        .pad
+        .link(&head_sink)
+        .context("failed to link decodebin to the video elements")?;
+
+    bus.wait_for_state_change_to(pipeline.as_ref(), State::Paused, preroll_timeout)
+        .context("failed to preroll")?;
+
+    // Sources which can't be seeked are captured as they play, like live
+    // sources.
+    let duration = pipeline.query_duration::<ClockTime>();
+    let duration = match duration {
+        Some(duration) if duration.is_some() && is_seekable(pipeline) => duration,
+        _ => {
+            ensure!(
+                task.timestamps.is_none(),
+                "can't sample at timestamps from a source which isn't seekable"
+            );
+            sink.set_drop(true);
+            bus.set_state(pipeline, State::Playing)
+                .context("failed to set pipeline state to playing")?;
+            live::capture(
+                sink,
+                &mut bus,
+                &task.live,
+                task.samples,
+                rng,
+                preroll_timeout,
+                |i, pos, sample| handle_sample(&output, i, pos, sample),
+            )?;
+            return Ok(TaskResult {
+                warnings: bus.warnings,
+            });
+        }
+    };
+    // Demuxers send the table of contents downstream along with the
+    // stream, and post it on the bus as well.
+    let toc = video_pad
+        .pad
+        .get_sticky_event(EventType::Toc, 0)
+        .and_then(|event| match event.view() {
+            EventView::Toc(toc) => Some(toc.get_toc_owned().0),
+            _ => None,
+        })
+        .or_else(|| bus.toc.take());
+    let chapters = toc
+        .map(|toc| chapters::chapters_from_toc(&toc, duration))
+        .unwrap_or_default();
+
+    let frames = pipeline
+        .query_duration::<gst::format::Default>()
+        .and_then(|frames| frames.0);
+    let frame_rate = FrameRate::new(video_pad.framerate, frames, duration);
+
+    // Generate sample offsets, unless they are given, along with the time
+    // of each.
+    let samples = match &task.timestamps {
+        Some(timestamps) => {
+            let mut samples = Vec::with_capacity(timestamps.len());
+            for &timestamp in timestamps {
+                let time = match timestamp {
+                    Timestamp::Time(time) => time,
+                    Timestamp::Frame(frame) => frame_rate
+                        .as_ref()
+                        .context("can't sample at frame numbers without a frame rate")?
+                        .time_of(frame),
+                };
+                samples.push((timestamp, time));
+            }
+            samples.sort_by_key(|&(_, time)| time);
+            if let Some(&(last, time)) = samples.last() {
+                ensure!(
+                    time < duration,
+                    "timestamp {} is beyond the end at {}",
+                    last,
+                    format_timestamp(duration)
+                );
+            }
+            let by_frame = samples
+                .iter()
+                .any(|(timestamp, _)| matches!(timestamp, Timestamp::Frame(_)));
+            if by_frame && frame_rate.as_ref().is_some_and(|rate| !rate.is_exact()) {
+                bus.warnings
+                    .push("variable frame rate, frames are sampled at estimated times".to_owned());
+            }
+            samples
+        }
+        None => {
+            let mut strategy = task.chapters;
+            if chapters.is_empty() && !matches!(strategy, ChapterSampling::Ignore) {
+                bus.warnings
+                    .push("no chapters found, sampling the whole video".to_owned());
+                strategy = ChapterSampling::Ignore;
+            }
+            strategy
+                .sample_offsets(&chapters, &task.constraints, duration, task.samples, rng)?
+                .into_iter()
+                .map(|time| (Timestamp::Time(time), time))
+                .collect()
+        }
+    };
+    let total = samples.len();
+
+    let seek_timeout = Duration::from_secs(config.seek_timeout);
+    let pull_preroll = |timeout| sink.try_pull_preroll(timeout);
+    let subtitles = if task.subtitles.is_enabled() {
+        match subtitles::read(&task.source, &task.subtitles, seek_timeout) {
+            Ok(Some(subtitles)) => Some(subtitles),
+            Ok(None) => {
+                bus.warnings.push("no subtitles found".to_owned());
+                None
+            }
+            // Samples are still useful without subtitles.
+            Err(err) => {
+                bus.warnings.push(format!("{:#}", err));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let subtitle_at = |pos| {
+        subtitles
+            .as_ref()
+            .and_then(|subtitles| subtitles.text_at(pos))
+    };
+    let mut last_pos = ClockTime::none();
+    let mut max_drift = 0;
+    let mut samples = VecDeque::from(samples);
+    let mut done = 0;
+    let mut retries = 0;
+    let mut skipped = 0;
+    while let Some((timestamp, seek_pos)) = samples.pop_front() {
+        // Seek to the given place and get the data buffer. Frames are
+        // seeked by number where elements support it, which is exact.
+        let flags = task.seek_mode.flags();
+        let mut rendered = None;
+        if let Some(overlays) = &overlays {
+            rendered = subtitle_at(seek_pos);
+            overlays.set_subtitle(rendered.as_deref())?;
+        }
+        let frame = match timestamp {
+            Timestamp::Frame(frame) if frame_rate.as_ref().is_some_and(FrameRate::is_exact) => {
+                pipeline
+                    .seek_simple(flags, gst::format::Default(Some(frame)))
+                    .ok()
+                    .map(|_| frame)
+            }
+            _ => None,
+        };
+        if frame.is_none() {
+            pipeline.seek_simple(flags, seek_pos)?;
+        }
+        let mut sample = pull_frame(&mut bus, seek_timeout, pull_preroll)
+            .with_context(|| format!("failed to get frame at {}", seek_pos))?;
+        // The frame covering the requested position usually starts a bit
+        // before it, and keyframes may be far from it.
+        let pos = sample_position(&sample).unwrap_or(seek_pos);
+        let subtitle = subtitle_at(pos);
+        if let Some(overlays) = &overlays {
+            // Render the subtitle of the keyframe instead of the one at the
+            // requested position.
+            if task.subtitles.render && subtitle != rendered {
+                overlays.set_subtitle(subtitle.as_deref())?;
+                pipeline.seek_simple(SeekFlags::FLUSH | SeekFlags::ACCURATE, pos)?;
+                sample = pull_frame(&mut bus, seek_timeout, pull_preroll)
+                    .with_context(|| format!("failed to get frame at {}", pos))?;
+            }
+        }
+        if let SeekMode::Accurate = task.seek_mode {
+            max_drift = max_drift.max(drift(seek_pos, pos).abs());
+        }
+        let meta = SampleMeta {
+            pos,
+            frame: match task.seek_mode {
+                // Keyframes are usually not where they were requested.
+                SeekMode::Accurate => frame,
+                SeekMode::Keyframe => None,
+            }
+            // Estimated frame numbers would be misleading in names.
+            .or_else(|| {
+                frame_rate
+                    .as_ref()
+                    .filter(|rate| rate.is_exact())?
+                    .frame_at(pos)
+            }),
+            chapter: chapters::title_at(&chapters, pos),
+            subtitle: subtitle.as_deref().filter(|_| task.subtitles.manifest),
+        };
+        // Nearby positions may end up at the same frame, and replacements
+        // at frames kept from earlier, which would be overwritten.
+        let taken =
+            pos == last_pos || task.replacing && task.target.join(file_name(&task, &meta)).exists();
+        if taken && task.replacing {
+            if retries < MAX_REPLACEMENT_RETRIES {
+                retries += 1;
+                let offset = task.constraints.sample_offsets(duration, 1, rng)?[0];
+                samples.push_back((Timestamp::Time(offset), offset));
+                continue;
+            }
+            skipped += 1;
+        }
+        if !taken {
+            let file = write_sample(&task, &output, &meta, &sample)?;
+            if let Some(manifest) = &mut manifest {
+                manifest.write_row(&ManifestRow {
+                    file: &file,
+                    requested: seek_pos,
+                    meta: &meta,
+                })?;
+            }
+            report_sample(&task.target.join(&file));
+            last_pos = pos;
+        }
+        done += 1;
+        report_progress(done as f64 / total as f64);
+        // Fail fast if anything went wrong in the pipeline meanwhile.
+        bus.drain()?;
+    }
+    if skipped > 0 {
+        bus.warnings.push(format!(
+            "{} replacements were skipped for lack of frames not sampled yet",
+            skipped
+        ));
+    }
+    if max_drift > MAX_ACCURATE_DRIFT.as_nanos() as i64 {
+        bus.warnings.push(format!(
+            "frames were up to {:.3}s away from the requested positions",
+            max_drift as f64 / 1e9
+        ));
+    }
+
+    Ok(TaskResult {
+        warnings: bus.warnings,
+    })
+}
+
+/// Delete samples written by the task, along with their rows in the
+/// manifest.
+pub fn remove_samples(task: &Task, paths: &[PathBuf]) -> Result<()> {
+    for path in paths {
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            // Let the user delete samples themselves.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to delete {}", path.display()))
+            }
+        }
+    }
+    let manifest_path = task.manifest_path();
+    if task.manifest && manifest_path.exists() {
+        let files = paths
+            .iter()
+            .filter_map(|path| path.file_name()?.to_str())
+            .collect::<Vec<_>>();
+        manifest::remove_rows(&manifest_path, &files)?;
+    }
+    Ok(())
+}
+
+/// Link the deinterlace element before convert if deinterlacing, or unlink
+/// it otherwise, and return the element to link the video stream to.
+fn video_head<'a>(
+    convert: &'a Element,
+    deinterlace: Option<&'a Element>,
+    config: &DeinterlaceConfig,
+    deinterlacing: bool,
+) -> Result<&'a Element> {
+    if !deinterlacing {
+        if let Some(deinterlace) = deinterlace {
+            deinterlace.unlink(convert);
+        }
+        return Ok(convert);
+    }
+    let deinterlace = match deinterlace {
+        Some(deinterlace) => deinterlace,
+        // Only fail for lack of the plugin when deinterlacing is forced.
+        None if config.mode == DeinterlaceMode::Auto => return Ok(convert),
+        None => bail!("deinterlace element is not available"),
+    };
+    deinterlace::configure(deinterlace, config, config.mode == DeinterlaceMode::On)?;
+    let convert_sink = convert.get_static_pad("sink").unwrap();
+    if !convert_sink.is_linked() {
+        deinterlace.link(convert)?;
+    }
+    Ok(deinterlace)
+}
+
+/// Wait for a frame with `pull`, e.g. `AppSink::try_pull_preroll`, which
+/// is given the timeout.
+fn pull_frame<F>(bus: &mut TaskBus, timeout: Duration, pull: F) -> Result<Sample>
+where
+    F: FnOnce(ClockTime) -> Option<Sample>,
+{
+    match pull(timeout.into()) {
+        Some(sample) => Ok(sample),
+        None => {
+            // Report the failure from the bus if there is any, since that
+            // is more informative than a timeout.
+            bus.drain()?;
+            bail!("timed out waiting for frame");
+        }
+    }
+}
+
+fn is_seekable(pipeline: &Pipeline) -> bool {
+    let mut query = gst::query::Seeking::new(Format::Time);
+    if !pipeline.query(&mut query) {
+        return false;
+    }
+    match query.view() {
+        QueryView::Seeking(seeking) => seeking.get_result().0,
+        _ => false,
+    }
+}
+
+/// Number of times replacements which land on frames already sampled are
+/// taken again at other positions, in total for a task.
+const MAX_REPLACEMENT_RETRIES: u32 = 20;
+
+/// Distance from the requested position beyond which accurate seeks are
+/// reported as misbehaving.
+const MAX_ACCURATE_DRIFT: Duration = Duration::from_secs(1);
+
+/// Nanoseconds from the requested position to the delivered position.
+fn drift(requested: ClockTime, position: ClockTime) -> i64 {
+    let requested = requested.nseconds().unwrap_or_default() as i64;
+    let position = position.nseconds().unwrap_or_default() as i64;
+    position - requested
+}
+
+/// Stream time of the frame in the sample.
+pub fn sample_position(sample: &Sample) -> Option<ClockTime> {
+    let pts = sample.get_buffer()?.get_pts();
+    let segment = sample.get_segment()?;
+    let segment = segment.downcast_ref::<ClockTime>()?;
+    Some(segment.to_stream_time(pts)).filter(|pos| pos.is_some())
+}
+
+/// Information of a sample to name it after and put in the manifest.
+struct SampleMeta<'a> {
+    /// Position of the frame sampled.
+    pos: ClockTime,
+    /// Frame number, if known.
+    frame: Option<u64>,
+    /// Title of the chapter the frame is in.
+    chapter: Option<&'a str>,
+    /// Subtitle shown at the frame.
+    subtitle: Option<&'a str>,
+}
+
+/// How frames of a stream are turned into images.
+struct FrameOutput {
+    /// Transfer function of HDR frames to be tone mapped.
+    hdr: Option<Transfer>,
+    /// Whether the images are sRGB.
+    srgb: bool,
+}
+
+/// Write the sample into a PNG file named after its position, and return
+/// the file name.
+fn write_sample(
+    task: &Task,
+    output: &FrameOutput,
+    meta: &SampleMeta,
+    sample: &Sample,
+) -> Result<String> {
+    let frame = RawFrame::from_sample(sample)?;
+    let image = match output.hdr {
+        Some(transfer) => hdr::tone_map(&frame, transfer, &task.hdr, task.format)?,
+        None => Image::from_frame(frame, task.format)?,
+    };
+
+    // Output to the image file.
+    let file_name = file_name(task, meta);
+    image.write_png(&task.target.join(&file_name), output.srgb)?;
+    Ok(file_name)
+}
+
+/// Expand the name template of the task for a sample.
+///
+/// The fields are `{prefix}`, `{index}`, `{min}`, `{sec}`, `{ms}`,
+/// `{frame}`, and `{chapter}`, which is the chapter title. Fields are empty
+/// when unknown. Unknown fields are kept as they are.
+fn file_name(task: &Task, meta: &SampleMeta) -> String {
+    let pos = meta.pos;
+    let mut result = String::new();
+    let mut rest = &*task.name_template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let field = match &rest[1..end] {
+            "prefix" => task.prefix.to_string(),
+            "index" => task.index.to_string(),
+            "min" => pos.minutes().unwrap().to_string(),
+            "sec" => (pos.seconds().unwrap() % 60).to_string(),
+            "ms" => format!("{:03}", pos.mseconds().unwrap() % 1000),
+            "frame" => meta
+                .frame
+                .map(|frame| frame.to_string())
+                .unwrap_or_default(),
+            // Titles may contain characters not allowed in file names.
+            "chapter" => meta
+                .chapter
+                .unwrap_or_default()
+                .replace(['/', '\\', '\0'], "_"),
+            _ => rest[..=end].to_owned(),
+        };
+        result.push_str(&field);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result.push_str(".png");
+    result
+}
+
+/// Set an enum property by the nick of the value.
+fn set_enum_property(element: &Element, name: &str, nick: &str) -> Result<()> {
+    let pspec = element
+        .find_property(name)
+        .with_context(|| format!("no property {}", name))?;
+    let value = EnumClass::new(pspec.get_value_type())
+        .and_then(|class| class.to_value_by_nick(nick))
+        .with_context(|| format!("unknown {} {:?}", name, nick))?;
+    element.set_property(name, &value)?;
+    Ok(())
+}
+
+struct AutoStateReset<T: IsA<Element>>(T);
+
+impl<T: IsA<Element>> Drop for AutoStateReset<T> {
+    fn drop(&mut self) {
+        let _ = self.0.set_state(State::Null);
+    }
+}
+
+/// Bus of the pipeline, collecting warnings posted on it during a task.
+struct TaskBus {
+    bus: Bus,
+    warnings: Vec<String>,
+    missing_plugins: Vec<MissingPlugin>,
+    /// Table of contents posted by the source, if any.
+    toc: Option<Toc>,
+}
+
+impl TaskBus {
+    fn new(bus: Bus) -> Self {
+        TaskBus {
+            bus,
+            warnings: Vec::new(),
+            missing_plugins: Vec::new(),
+            toc: None,
+        }
+    }
+
+    /// Change the state of the pipeline, and report the error posted on
+    /// the bus if it fails, which tells why unlike the failure itself.
+    fn set_state(&mut self, pipeline: &Pipeline, state: State) -> Result<StateChangeSuccess> {
+        match pipeline.set_state(state) {
+            Ok(success) => Ok(success),
+            Err(e) => {
+                self.drain()?;
+                Err(e.into())
+            }
+        }
+    }
+
+    fn wait_for_state_change_to(
+        &mut self,
+        src: &Object,
+        state: State,
+        timeout: Duration,
+    ) -> Result<()> {
+        self.wait_for_message_from(src, timeout, |view| match view {
+            MessageView::StateChanged(view) => view.get_current() == state,
+            _ => false,
+        })
+    }
+
+    /// Wait on the bus until there is a message from `src` matching the
+    /// predicate, and fail if it doesn't come in time or if anything in the
+    /// pipeline fails before that.
+    fn wait_for_message_from<P: Fn(MessageView) -> bool>(
+        &mut self,
+        src: &Object,
+        timeout: Duration,
+        predicate: P,
+    ) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let msg = self
+                .bus
+                .timed_pop(remaining.into())
+                .ok_or_else(|| anyhow!("timed out after {}s", timeout.as_secs()))?;
+            self.handle(&msg)?;
+            if msg.get_src().as_ref() == Some(src) && predicate(msg.view()) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handle messages on the bus until the deadline.
+    fn idle_until(&mut self, deadline: Instant) -> Result<()> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                return Ok(());
+            }
+            match self.bus.timed_pop(remaining.into()) {
+                Some(msg) => self.handle(&msg)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Handle all the messages currently on the bus.
+    fn drain(&mut self) -> Result<()> {
+        while let Some(msg) = self.bus.pop() {
+            self.handle(&msg)?;
+        }
+        Ok(())
+    }
+
+    /// Fail if any element has reported a missing plugin.
+    fn check_missing_plugins(&mut self) -> Result<()> {
+        if self.missing_plugins.is_empty() {
+            return Ok(());
+        }
+        Err(MissingPlugins(mem::take(&mut self.missing_plugins)).into())
+    }
+
+    /// Collect warnings and missing plugins, and turn error and
+    /// end-of-stream messages into failure of the task.
+    fn handle(&mut self, msg: &Message) -> Result<()> {
+        match msg.view() {
+            MessageView::Error(err) => {
+                // Missing plugins are usually what the error is about,
+                // and they are more useful to report.
+                self.check_missing_plugins()?;
+                bail!(describe_message(msg, err.get_error(), err.get_debug()))
+            }
+            MessageView::Element(_) => {
+                if let Some(plugin) = MissingPlugin::from_message(msg) {
+                    self.missing_plugins.push(plugin);
+                }
+            }
+            MessageView::Warning(warning) => {
+                let warning = describe_message(msg, warning.get_error(), warning.get_debug());
+                self.warnings.push(warning);
+            }
+            MessageView::Toc(toc) => self.toc = Some(toc.get_toc().0),
+            MessageView::Eos(_) => bail!("unexpected end of stream"),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn describe_message(msg: &Message, error: glib::Error, debug: Option<String>) -> String {
+    let mut result = match msg.get_src() {
+        Some(src) => format!("{}: {}", src.get_path_string(), error),
+        None => error.to_string(),
+    };
+    if let Some(debug) = debug {
+        result.push('\n');
+        result.push_str(&debug);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gst::MessageType;
+    use std::{env, process};
+    use url::Url;
+
+    /// Number of clips sampled in each round of the benchmark.
+    const CLIPS: u32 = 50;
+
+    /// Compare sampling short clips with the pipeline reused across tasks
+    /// and with a fresh pipeline for each task, like before it was reused.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture pipeline_reuse`.
+    #[test]
+    #[ignore]
+    fn bench_pipeline_reuse() {
+        gst::init().unwrap();
+        let dir = env::temp_dir().join(format!("video-sampler-bench-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let clips = (0..CLIPS).map(|i| make_clip(&dir, i)).collect::<Vec<_>>();
+        let run = |reuse: bool| {
+            let mut ctx = TaskContext::new(SamplerConfig::default());
+            let start = Instant::now();
+            for (i, clip) in clips.iter().enumerate() {
+                if !reuse {
+                    ctx.pipeline = None;
+                }
+                let task = bench_task(&dir, clip, i as u32);
+                run_task(&mut ctx, task, |_| {}, |_| {}).unwrap();
+            }
+            start.elapsed() / CLIPS
+        };
+        // Load the plugins and warm the file cache before measuring.
+        run(true);
+        let reused = run(true);
+        let fresh = run(false);
+        fs::remove_dir_all(&dir).unwrap();
+        println!(
+            "per file: {:?} reused, {:?} fresh, {:?} saved",
+            reused,
+            fresh,
+            fresh.saturating_sub(reused),
+        );
+    }
+
+    /// Encode a two-second test clip.
+    fn make_clip(dir: &Path, index: u32) -> PathBuf {
+        let path = dir.join(format!("clip-{}.avi", index));
+        let description = format!(
+            "videotestsrc num-buffers=50 \
+             ! video/x-raw,width=640,height=360,framerate=25/1 \
+             ! jpegenc ! avimux ! filesink location={}",
+            path.display(),
+        );
+        let pipeline = gst::parse_launch(&description).unwrap();
+        pipeline.set_state(State::Playing).unwrap();
+        let msg = pipeline
+            .get_bus()
+            .unwrap()
+            .timed_pop_filtered(
+                gst::CLOCK_TIME_NONE,
+                &[MessageType::Eos, MessageType::Error],
+            )
+            .unwrap();
+        pipeline.set_state(State::Null).unwrap();
+        assert_eq!(msg.get_type(), MessageType::Eos, "failed to encode clip");
+        path
+    }
+
+    fn bench_task(dir: &Path, clip: &Path, index: u32) -> Task {
+        Task {
+            prefix: Arc::from("bench"),
+            height: 120,
+            samples: 5,
+            target: Arc::from(dir),
+            index,
+            source: String::from(Url::from_file_path(clip).unwrap()).into_boxed_str(),
+            stream: StreamSelection::default(),
+            live: LiveCapture::default(),
+            constraints: TimeConstraints::default(),
+            timestamps: None,
+            chapters: ChapterSampling::Ignore,
+            name_template: Arc::from("{prefix}-{index}-{min}-{sec}-{ms}"),
+            seek_mode: SeekMode::default(),
+            manifest: false,
+            format: OutputFormat::default(),
+            hdr: HdrConfig::default(),
+            deinterlace: DeinterlaceConfig::default(),
+            color: ColorConfig::default(),
+            overlay: OverlayConfig::default(),
+            display_name: Box::from("clip"),
+            subtitles: SubtitleConfig::default(),
+            replacing: false,
+            ref_idx: 0,
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Encoding of sampled frames into image files.
+
+use anyhow::{bail, ensure, Context, Result};
+use gst::Sample;
+use png::{BitDepth, ColorType, SrgbRenderingIntent};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Pixel format of output images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Rgb8,
+    Rgba8,
+    Rgb16,
+    Rgba16,
+    Gray8,
+    Gray16,
+}
+
+impl OutputFormat {
+    /// Raw video format to request from videoconvert.
+    pub(super) fn caps_format(self) -> &'static str {
+        match self {
+            OutputFormat::Rgb8 => "RGB",
+            OutputFormat::Rgba8 => "RGBA",
+            OutputFormat::Rgb16 | OutputFormat::Rgba16 => "ARGB64",
+            OutputFormat::Gray8 => "GRAY8",
+            OutputFormat::Gray16 => "GRAY16_LE",
+        }
+    }
+
+    fn color(self) -> ColorType {
+        match self {
+            OutputFormat::Rgb8 | OutputFormat::Rgb16 => ColorType::Rgb,
+            OutputFormat::Rgba8 | OutputFormat::Rgba16 => ColorType::Rgba,
+            OutputFormat::Gray8 | OutputFormat::Gray16 => ColorType::Grayscale,
+        }
+    }
+
+    fn depth(self) -> BitDepth {
+        match self {
+            OutputFormat::Rgb8 | OutputFormat::Rgba8 | OutputFormat::Gray8 => BitDepth::Eight,
+            OutputFormat::Rgb16 | OutputFormat::Rgba16 | OutputFormat::Gray16 => BitDepth::Sixteen,
+        }
+    }
+
+    /// Append a pixel with non-linear values in the range of 0 to 1.
+    pub(super) fn push_pixel(self, data: &mut Vec<u8>, [r, g, b]: [f32; 3], alpha: f32) {
+        let channels = match self {
+            OutputFormat::Rgb8 | OutputFormat::Rgb16 => vec![r, g, b],
+            OutputFormat::Rgba8 | OutputFormat::Rgba16 => vec![r, g, b, alpha],
+            // Luma as in BT.709.
+            OutputFormat::Gray8 | OutputFormat::Gray16 => {
+                vec![0.2126 * r + 0.7152 * g + 0.0722 * b]
+            }
+        };
+        for value in channels {
+            let value = value.clamp(0., 1.);
+            match self.depth() {
+                BitDepth::Sixteen => {
+                    let value = (value * u16::MAX as f32).round() as u16;
+                    data.extend_from_slice(&value.to_be_bytes());
+                }
+                _ => data.push((value * u8::MAX as f32).round() as u8),
+            }
+        }
+    }
+}
+
+/// Frame in the layout the PNG encoder takes, i.e. packed rows with
+/// big-endian samples.
+pub(super) struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorType,
+    pub depth: BitDepth,
+    pub data: Vec<u8>,
+}
+
+/// Raw video frame with the padding of rows removed.
+pub struct RawFrame {
+    pub width: usize,
+    pub height: usize,
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+impl RawFrame {
+    pub fn from_sample(sample: &Sample) -> Result<Self> {
+        let caps = sample.get_caps().context("failed to get caps")?;
+        let s = caps.get_structure(0).context("failed to get caps")?;
+        let width = s.get_some::<i32>("width").ok().context("no width")? as usize;
+        let height = s.get_some::<i32>("height").ok().context("no height")? as usize;
+        let format = s
+            .get::<String>("format")
+            .ok()
+            .flatten()
+            .context("no format")?;
+        let pixel_size = match format.as_str() {
+            "GRAY8" => 1,
+            "GRAY16_LE" => 2,
+            "RGB" => 3,
+            "RGBA" => 4,
+            "ARGB64" => 8,
+            _ => bail!("unexpected format {}", format),
+        };
+        let buffer = sample.get_buffer().context("failed to get buffer")?;
+        let buffer = buffer.map_readable()?;
+        let buffer = buffer.as_slice();
+        // Rows of raw video are padded to multiples of four bytes.
+        let row_size = width * pixel_size;
+        let stride = (row_size + 3) & !3;
+        ensure!(buffer.len() >= stride * height, "buffer too small");
+        let data = (0..height)
+            .flat_map(|y| &buffer[y * stride..y * stride + row_size])
+            .copied()
+            .collect::<Vec<_>>();
+        Ok(RawFrame {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    /// 16-bit samples of a frame in a 16-bit format.
+    pub fn samples16(&self) -> impl Iterator<Item = u16> + '_ {
+        let little_endian = self.format.ends_with("_LE");
+        self.data.chunks_exact(2).map(move |bytes| {
+            let bytes = [bytes[0], bytes[1]];
+            if little_endian {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_ne_bytes(bytes)
+            }
+        })
+    }
+}
+
+impl Image {
+    /// Convert the frame from the raw format requested for the output
+    /// format.
+    pub fn from_frame(frame: RawFrame, format: OutputFormat) -> Result<Self> {
+        ensure!(
+            frame.format == format.caps_format(),
+            "unexpected format {}",
+            frame.format
+        );
+        let data = match format {
+            OutputFormat::Rgb16 | OutputFormat::Rgba16 => {
+                let alpha = format == OutputFormat::Rgba16;
+                let samples = frame.samples16().collect::<Vec<_>>();
+                let mut data = Vec::with_capacity(samples.len() * 2);
+                for pixel in samples.chunks_exact(4) {
+                    let channels = if alpha { &[1, 2, 3, 0][..] } else { &[1, 2, 3] };
+                    for &i in channels {
+                        data.extend_from_slice(&pixel[i].to_be_bytes());
+                    }
+                }
+                data
+            }
+            OutputFormat::Gray16 => frame
+                .samples16()
+                .flat_map(|value| value.to_be_bytes())
+                .collect(),
+            // 8-bit formats are laid out the same.
+            _ => frame.data,
+        };
+        Ok(Image {
+            width: frame.width as u32,
+            height: frame.height as u32,
+            color: format.color(),
+            depth: format.depth(),
+            data,
+        })
+    }
+
+    /// Pack pixels with non-linear values in the range of 0 to 1.
+    pub fn from_pixels<I>(width: usize, height: usize, format: OutputFormat, pixels: I) -> Self
+    where
+        I: Iterator<Item = ([f32; 3], f32)>,
+    {
+        let mut data = Vec::new();
+        for (rgb, alpha) in pixels {
+            format.push_pixel(&mut data, rgb, alpha);
+        }
+        Image {
+            width: width as u32,
+            height: height as u32,
+            color: format.color(),
+            depth: format.depth(),
+            data,
+        }
+    }
+
+    /// Write the image as PNG, marked as sRGB if `srgb` is set.
+    pub fn write_png(&self, path: &Path, srgb: bool) -> Result<()> {
+        let output = File::create(path).context("failed to create output file")?;
+        let output = BufWriter::new(output);
+        let mut encoder = png::Encoder::new(output, self.width, self.height);
+        encoder.set_color(self.color);
+        encoder.set_depth(self.depth);
+        if srgb {
+            // This adds matching gAMA and cHRM chunks for older decoders.
+            encoder.set_srgb(SrgbRenderingIntent::Perceptual);
+        }
+        encoder
+            .write_header()
+            .context("failed to write header")?
+            .write_image_data(&self.data)
+            .context("failed to write image data")?;
+        Ok(())
+    }
+}
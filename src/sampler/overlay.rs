@@ -0,0 +1,163 @@
+//! Text burnt into samples to tell where they came from.
+
+use super::set_enum_property;
+use anyhow::{Context, Result};
+use gst::prelude::*;
+use gst::{Element, ElementFactory, Pipeline, State};
+use serde::{Deserialize, Serialize};
+use std::iter;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OverlayConfig {
+    /// Whether to show the position of the frame.
+    pub timestamp: bool,
+    /// Whether to show the name of the video.
+    pub file_name: bool,
+    /// Custom text to show.
+    pub text: Option<String>,
+    /// Font family, e.g. `Sans` or `Monospace`.
+    pub font: String,
+    /// Font size in points.
+    pub size: u32,
+    /// Horizontal alignment: `left`, `center` or `right`.
+    pub halign: String,
+    /// Vertical alignment: `top`, `center` or `bottom`.
+    pub valign: String,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        OverlayConfig {
+            timestamp: false,
+            file_name: false,
+            text: None,
+            font: "Sans".to_owned(),
+            size: 12,
+            halign: "left".to_owned(),
+            valign: "bottom".to_owned(),
+        }
+    }
+}
+
+impl OverlayConfig {
+    fn is_enabled(&self) -> bool {
+        self.timestamp || self.file_name || self.text.is_some()
+    }
+}
+
+/// Overlay elements inserted between videoscale and the sink for a task,
+/// which are removed on drop. It should be dropped after the pipeline is
+/// stopped.
+pub(super) struct OverlayGuard {
+    pipeline: Pipeline,
+    scale: Element,
+    sink: Element,
+    elements: Vec<Element>,
+    /// Overlay rendering the subtitle, if subtitles are rendered.
+    subtitle: Option<Element>,
+}
+
+impl OverlayGuard {
+    /// Set the subtitle rendered onto the frames from the next seek.
+    pub fn set_subtitle(&self, text: Option<&str>) -> Result<()> {
+        if let Some(subtitle) = &self.subtitle {
+            // Cues are plain text, but are rendered as Pango markup.
+            let text = glib::markup_escape_text(text.unwrap_or_default());
+            subtitle.set_property("text", &text.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OverlayGuard {
+    fn drop(&mut self) {
+        for element in &self.elements {
+            let _ = element.set_state(State::Null);
+        }
+        let elements = self.elements.iter().collect::<Vec<_>>();
+        let _ = self.pipeline.remove_many(&elements);
+        let _ = self.scale.link(&self.sink);
+    }
+}
+
+/// Insert the overlay elements if the config asks for any text, or if
+/// subtitles are rendered.
+pub(super) fn insert(
+    pipeline: &Pipeline,
+    scale: &Element,
+    sink: &Element,
+    config: &OverlayConfig,
+    file_name: &str,
+    subtitles: bool,
+) -> Result<Option<OverlayGuard>> {
+    if !config.is_enabled() && !subtitles {
+        return Ok(None);
+    }
+    let mut elements = Vec::new();
+    // The subtitle is set for each sample, rather than fed from a subtitle
+    // stream. textoverlay defaults to the usual place of subtitles.
+    let subtitle = if subtitles {
+        let subtitle = ElementFactory::make("textoverlay", None)
+            .context("textoverlay element is not available")?;
+        elements.push(subtitle.clone());
+        Some(subtitle)
+    } else {
+        None
+    };
+    if config.is_enabled() {
+        elements.push(make_overlay(config, file_name)?);
+    }
+    // Overlays can only blend into some formats, so convert back to what
+    // the sink wants afterwards.
+    elements.push(ElementFactory::make("videoconvert", None)?);
+
+    scale.unlink(sink);
+    let guard = OverlayGuard {
+        pipeline: pipeline.clone(),
+        scale: scale.clone(),
+        sink: sink.clone(),
+        elements,
+        subtitle,
+    };
+    let elements = guard.elements.iter().collect::<Vec<_>>();
+    pipeline.add_many(&elements)?;
+    let chain = iter::once(scale)
+        .chain(elements)
+        .chain(iter::once(sink))
+        .collect::<Vec<_>>();
+    Element::link_many(&chain)?;
+    Ok(Some(guard))
+}
+
+fn make_overlay(config: &OverlayConfig, file_name: &str) -> Result<Element> {
+    // timeoverlay shows the time after its text, and textoverlay just the
+    // text.
+    let factory = if config.timestamp {
+        "timeoverlay"
+    } else {
+        "textoverlay"
+    };
+    let overlay = ElementFactory::make(factory, None)
+        .with_context(|| format!("{} element is not available", factory))?;
+
+    let mut text = Vec::new();
+    if config.file_name {
+        text.push(file_name);
+    }
+    if let Some(custom) = &config.text {
+        text.push(custom);
+    }
+    // The text is rendered as Pango markup.
+    let text = glib::markup_escape_text(&text.join(" · "));
+    overlay.set_property("text", &text.as_str())?;
+    let font = format!("{} {}", config.font, config.size);
+    overlay.set_property("font-desc", &font)?;
+    overlay.set_property("shaded-background", &true)?;
+    set_enum_property(&overlay, "halignment", &config.halign)?;
+    set_enum_property(&overlay, "valignment", &config.valign)?;
+    if config.timestamp {
+        set_enum_property(&overlay, "time-mode", "stream-time")?;
+    }
+    Ok(overlay)
+}
@@ -0,0 +1,282 @@
+//! Restricting the part of a video samples are taken from.
+
+use anyhow::{ensure, Result};
+use gst::ClockTime;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Parts of the video to take samples from.
+///
+/// Samples are taken from the include ranges, or the whole video if there
+/// are none, minus the exclude ranges and the skipped start and end.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimeConstraints {
+    /// Part of the start to skip, e.g. an intro.
+    pub skip_start: Offset,
+    /// Part of the end to skip, e.g. credits.
+    pub skip_end: Offset,
+    pub include: Vec<TimeRange>,
+    pub exclude: Vec<TimeRange>,
+}
+
+/// Length of a part of the video, as the sum of an absolute time and a
+/// percentage of the duration.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Offset {
+    pub seconds: f64,
+    pub percent: f64,
+}
+
+/// Time range in seconds from the start of the video.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Offset {
+    fn nseconds(&self, duration: u64) -> u64 {
+        let nseconds = self.seconds * 1e9 + self.percent / 100. * duration as f64;
+        (nseconds.max(0.) as u64).min(duration)
+    }
+}
+
+impl TimeRange {
+    fn nseconds(&self) -> (u64, u64) {
+        let to_nseconds = |seconds: f64| (seconds.max(0.) * 1e9) as u64;
+        (to_nseconds(self.start), to_nseconds(self.end))
+    }
+}
+
+impl TimeConstraints {
+    /// Generate sorted random sample offsets in the allowed parts of a video
+    /// with the given duration.
+    pub fn sample_offsets(
+        &self,
+        duration: ClockTime,
+        samples: u32,
+        rng: &mut ThreadRng,
+    ) -> Result<Vec<ClockTime>> {
+        self.sample_offsets_in(
+            duration,
+            (ClockTime::from_nseconds(0), duration),
+            samples,
+            rng,
+        )
+    }
+
+    /// Generate sorted random sample offsets in the allowed parts of the
+    /// given range of a video.
+    pub fn sample_offsets_in(
+        &self,
+        duration: ClockTime,
+        (start, end): (ClockTime, ClockTime),
+        samples: u32,
+        rng: &mut ThreadRng,
+    ) -> Result<Vec<ClockTime>> {
+        let duration = duration.nseconds().unwrap_or_default();
+        let (start, end) = (
+            start.nseconds().unwrap_or_default(),
+            end.nseconds().unwrap_or(duration),
+        );
+        let ranges = self
+            .allowed_ranges(duration)
+            .into_iter()
+            .map(|(range_start, range_end)| (range_start.max(start), range_end.min(end)))
+            .filter(|(start, end)| start < end)
+            .collect::<Vec<_>>();
+        let total = ranges.iter().map(|(start, end)| end - start).sum::<u64>();
+        ensure!(
+            total > 0,
+            "no part of the video left by the time constraints"
+        );
+
+        let mut offsets = (0..samples)
+            .map(|_| {
+                // Pick a point in the concatenation of the ranges, and find
+                // where it is in the video.
+                let mut pos = rng.gen_range(0..total);
+                for &(start, end) in &ranges {
+                    if pos < end - start {
+                        return ClockTime::from_nseconds(start + pos);
+                    }
+                    pos -= end - start;
+                }
+                unreachable!()
+            })
+            .collect::<Vec<_>>();
+        offsets.sort();
+        Ok(offsets)
+    }
+
+    /// Whether the position is in the allowed parts of a video.
+    pub fn allows(&self, duration: ClockTime, pos: ClockTime) -> bool {
+        let duration = duration.nseconds().unwrap_or_default();
+        let pos = pos.nseconds().unwrap_or_default();
+        self.allowed_ranges(duration)
+            .iter()
+            .any(|&(start, end)| start <= pos && pos < end)
+    }
+
+    /// Sorted, disjoint and non-empty ranges in nanoseconds to sample from.
+    fn allowed_ranges(&self, duration: u64) -> Vec<(u64, u64)> {
+        let start = self.skip_start.nseconds(duration);
+        let end = duration.saturating_sub(self.skip_end.nseconds(duration));
+        let mut ranges = if self.include.is_empty() {
+            vec![(start, end)]
+        } else {
+            let mut ranges = self
+                .include
+                .iter()
+                .map(|range| {
+                    let (range_start, range_end) = range.nseconds();
+                    (range_start.max(start), range_end.min(end))
+                })
+                .filter(|(start, end)| start < end)
+                .collect::<Vec<_>>();
+            ranges.sort_unstable();
+            // Merge overlapping ranges so no part is more likely than others.
+            let mut merged = Vec::<(u64, u64)>::with_capacity(ranges.len());
+            for (start, end) in ranges {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            merged
+        };
+        for range in &self.exclude {
+            let (exclude_start, exclude_end) = range.nseconds();
+            // Reversed ranges would split ranges into overlapping ones.
+            if exclude_start >= exclude_end {
+                continue;
+            }
+            ranges = ranges
+                .into_iter()
+                .flat_map(|(start, end)| {
+                    let before = (start, end.min(exclude_start));
+                    let after = (start.max(exclude_end), end);
+                    vec![before, after]
+                })
+                .filter(|(start, end)| start < end)
+                .collect();
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn range(start: f64, end: f64) -> TimeRange {
+        TimeRange { start, end }
+    }
+
+    #[test]
+    fn allows_whole_video_without_constraints() {
+        let constraints = TimeConstraints::default();
+        assert_eq!(
+            constraints.allowed_ranges(100 * SECOND),
+            vec![(0, 100 * SECOND)],
+        );
+    }
+
+    #[test]
+    fn skips_start_and_end() {
+        let constraints = TimeConstraints {
+            skip_start: Offset {
+                seconds: 5.,
+                percent: 0.,
+            },
+            skip_end: Offset {
+                seconds: 0.,
+                percent: 10.,
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.allowed_ranges(100 * SECOND),
+            vec![(5 * SECOND, 90 * SECOND)],
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_includes() {
+        let constraints = TimeConstraints {
+            include: vec![
+                range(30., 40.),
+                range(10., 20.),
+                range(15., 25.),
+                range(25., 28.),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.allowed_ranges(100 * SECOND),
+            vec![(10 * SECOND, 28 * SECOND), (30 * SECOND, 40 * SECOND)],
+        );
+    }
+
+    #[test]
+    fn excludes_from_merged_includes() {
+        let constraints = TimeConstraints {
+            skip_start: Offset {
+                seconds: 12.,
+                percent: 0.,
+            },
+            include: vec![range(10., 30.), range(20., 50.)],
+            exclude: vec![range(25., 35.), range(45., 60.)],
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.allowed_ranges(100 * SECOND),
+            vec![(12 * SECOND, 25 * SECOND), (35 * SECOND, 45 * SECOND)],
+        );
+    }
+
+    #[test]
+    fn ignores_reversed_excludes() {
+        let constraints = TimeConstraints {
+            include: vec![range(10., 100.)],
+            exclude: vec![range(50., 20.)],
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.allowed_ranges(100 * SECOND),
+            vec![(10 * SECOND, 100 * SECOND)],
+        );
+    }
+
+    #[test]
+    fn samples_within_allowed_parts() {
+        let constraints = TimeConstraints {
+            include: vec![range(10., 20.)],
+            exclude: vec![range(12., 18.)],
+            ..Default::default()
+        };
+        let duration = ClockTime::from_seconds(100);
+        let offsets = constraints
+            .sample_offsets(duration, 50, &mut thread_rng())
+            .unwrap();
+        assert_eq!(offsets.len(), 50);
+        assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(offsets.iter().all(|&pos| constraints.allows(duration, pos)));
+    }
+
+    #[test]
+    fn fails_without_allowed_parts() {
+        let constraints = TimeConstraints {
+            exclude: vec![range(0., 100.)],
+            ..Default::default()
+        };
+        let duration = ClockTime::from_seconds(100);
+        assert!(constraints
+            .sample_offsets(duration, 1, &mut thread_rng())
+            .is_err());
+    }
+}
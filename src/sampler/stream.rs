@@ -0,0 +1,161 @@
+use gst::prelude::*;
+use gst::{EventType, EventView, Fraction, GhostPad, Pad};
+use serde::{Deserialize, Serialize};
+
+/// How to choose the video stream to sample from a file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "by", rename_all = "kebab-case")]
+pub enum StreamSelection {
+    /// The first video stream which is not a still picture.
+    #[default]
+    First,
+    /// The video stream at the given index among all video streams,
+    /// including still pictures.
+    Index { index: u32 },
+    /// The first video stream in the given language.
+    Language { language: String },
+    /// The video stream with the most pixels.
+    LargestResolution,
+}
+
+/// A video stream exposed by decodebin.
+pub struct VideoPad {
+    pub pad: Pad,
+    pub width: i32,
+    pub height: i32,
+    /// Frame rate, which is none for variable frame rate.
+    pub framerate: Option<Fraction>,
+    pub colorimetry: Option<String>,
+    /// Whether the frames are interlaced, in any of the ways caps describe.
+    pub interlaced: bool,
+    language: Option<String>,
+    /// Whether the stream is a still picture, e.g. an attached cover.
+    still: bool,
+    /// Whether the stream is decoded, rather than exposed with its encoded
+    /// caps for lack of a decoder.
+    decoded: bool,
+}
+
+impl VideoPad {
+    pub fn new(pad: Pad) -> Option<Self> {
+        let caps = pad.get_current_caps()?;
+        let s = caps.get_structure(0)?;
+        if !s.get_name().starts_with("video/") {
+            return None;
+        }
+        let width = s.get_some::<i32>("width").ok()?;
+        let height = s.get_some::<i32>("height").ok()?;
+        let framerate = s
+            .get_some::<Fraction>("framerate")
+            .ok()
+            .filter(|framerate| *framerate.numer() > 0 && *framerate.denom() > 0);
+        let colorimetry = s.get::<String>("colorimetry").ok().flatten();
+        let interlaced = s
+            .get::<String>("interlace-mode")
+            .ok()
+            .flatten()
+            .is_some_and(|mode| mode != "progressive");
+        let decoded = s.get_name() == "video/x-raw";
+        let language = get_language(&pad);
+        let still = is_from_image(&pad);
+        Some(VideoPad {
+            pad,
+            width,
+            height,
+            framerate,
+            colorimetry,
+            interlaced,
+            language,
+            still,
+            decoded,
+        })
+    }
+}
+
+impl VideoPad {
+    pub fn is_still(&self) -> bool {
+        self.still
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        self.decoded
+    }
+}
+
+impl StreamSelection {
+    /// Choose a stream from the video streams in the order of the file.
+    ///
+    /// Selecting by language falls back to the first stream when there is
+    /// no stream in the language.
+    pub fn select<'a>(&self, pads: &'a [VideoPad]) -> Option<&'a VideoPad> {
+        let mut videos = pads.iter().filter(|pad| !pad.still);
+        match self {
+            StreamSelection::First => videos.next(),
+            StreamSelection::Index { index } => pads.get(*index as usize),
+            StreamSelection::Language { language } => videos
+                .clone()
+                .find(|pad| match &pad.language {
+                    Some(lang) => lang.eq_ignore_ascii_case(language),
+                    None => false,
+                })
+                .or_else(|| videos.next()),
+            StreamSelection::LargestResolution => {
+                videos.max_by_key(|pad| i64::from(pad.width) * i64::from(pad.height))
+            }
+        }
+    }
+}
+
+fn get_language(pad: &Pad) -> Option<String> {
+    let event = pad.get_sticky_event(EventType::Tag, 0)?;
+    let tags = match event.view() {
+        EventView::Tag(tag) => tag.get_tag().to_owned(),
+        _ => return None,
+    };
+    let language = tags.get::<gst::tags::LanguageCode>()?;
+    language.get().map(String::from)
+}
+
+/// Check whether the stream is decoded from an image format, which is how
+/// attached pictures show up.
+fn is_from_image(pad: &Pad) -> bool {
+    let mut pad = pad.clone();
+    loop {
+        // Step into the bins through ghost pads.
+        if let Some(target) = pad
+            .downcast_ref::<GhostPad>()
+            .and_then(|pad| pad.get_target())
+        {
+            pad = target;
+            continue;
+        }
+        // Walk upstream while the data is still raw video, until reaching
+        // the decoder.
+        let element = match pad.get_parent_element() {
+            Some(element) => element,
+            None => return false,
+        };
+        let sink = match element.get_sink_pads().into_iter().next() {
+            Some(sink) => sink,
+            None => return false,
+        };
+        let caps = match sink.get_current_caps() {
+            Some(caps) => caps,
+            None => return false,
+        };
+        let name = match caps.get_structure(0) {
+            Some(s) => s.get_name(),
+            None => return false,
+        };
+        if name.starts_with("image/") {
+            return true;
+        }
+        if name != "video/x-raw" {
+            return false;
+        }
+        pad = match sink.get_peer() {
+            Some(peer) => peer,
+            None => return false,
+        };
+    }
+}
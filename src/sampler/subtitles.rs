@@ -0,0 +1,229 @@
+//! Subtitles to render onto samples and list in the manifest.
+//!
+//! Subtitle streams are sparse, so the text at a position can't be waited
+//! for after seeking. Instead, the cues are read ahead of sampling in a
+//! pipeline of their own.
+
+use super::{sample_position, AutoStateReset};
+use anyhow::{bail, ensure, Context, Result};
+use glib::{Cast, EnumClass, ObjectExt};
+use gst::prelude::*;
+use gst::{Caps, ClockTime, ElementFactory, MessageType, MessageView, Pipeline, Sample, State};
+use gst_app::AppSink;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use url::Url;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SubtitleConfig {
+    /// Whether to render the subtitle onto samples.
+    pub render: bool,
+    /// Whether to write the subtitle at each sample into the manifest.
+    pub manifest: bool,
+    /// Index of the embedded subtitle stream to use when there is no
+    /// subtitle file next to the video.
+    pub stream: u32,
+}
+
+impl SubtitleConfig {
+    pub(super) fn is_enabled(&self) -> bool {
+        self.render || self.manifest
+    }
+}
+
+/// Extensions of subtitle files looked for next to videos, in order of
+/// preference.
+const SIDECAR_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "vtt"];
+
+/// How often to check the bus while waiting for cues.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Text shown from `start` until `end`, in nanoseconds of stream time.
+struct Cue {
+    start: u64,
+    end: Option<u64>,
+    text: String,
+}
+
+impl Cue {
+    fn from_sample(sample: &Sample) -> Option<Self> {
+        let start = sample_position(sample)?.nseconds()?;
+        let buffer = sample.get_buffer()?;
+        let end = buffer
+            .get_duration()
+            .nseconds()
+            .map(|duration| start + duration);
+        let markup = sample
+            .get_caps()
+            .and_then(|caps| caps.get_structure(0)?.get::<String>("format").ok()?)
+            .is_some_and(|format| format == "pango-markup");
+        let buffer = buffer.map_readable().ok()?;
+        let text = String::from_utf8_lossy(buffer.as_slice());
+        let text = if markup {
+            strip_markup(&text)
+        } else {
+            text.into_owned()
+        };
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        Some(Cue {
+            start,
+            end,
+            text: text.to_owned(),
+        })
+    }
+}
+
+/// Cues of a subtitle stream in the order of their start.
+pub(super) struct Subtitles {
+    cues: Vec<Cue>,
+}
+
+impl Subtitles {
+    /// Text shown at the position, with a line for each cue.
+    pub fn text_at(&self, pos: ClockTime) -> Option<String> {
+        let pos = pos.nseconds()?;
+        let lines = self
+            .cues
+            .iter()
+            .filter(|cue| cue.start <= pos && cue.end.is_none_or(|end| pos < end))
+            .map(|cue| cue.text.as_str())
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+/// Read the subtitle file next to the source if there is one, or the
+/// embedded subtitle stream otherwise, and return none if there is neither.
+pub(super) fn read(
+    source: &str,
+    config: &SubtitleConfig,
+    timeout: Duration,
+) -> Result<Option<Subtitles>> {
+    let (uri, index) = match find_sidecar(source) {
+        Some(uri) => (uri, 0),
+        None => (source.to_owned(), config.stream),
+    };
+
+    let pipeline = Pipeline::new(None);
+    let decodebin = ElementFactory::make("uridecodebin", None)?;
+    decodebin.set_property("uri", &uri)?;
+    // Only expose text streams, and don't waste time decoding the others.
+    decodebin.set_property("caps", &Caps::new_simple("text/x-raw", &[]))?;
+    decodebin.set_property("expose-all-streams", &false)?;
+    let select_result = glib::Type::from_name("GstAutoplugSelectResult")
+        .and_then(EnumClass::new)
+        .context("autoplug-select result type not found")?;
+    decodebin.connect("autoplug-select", false, move |values| {
+        let factory = values[3].get::<ElementFactory>().ok().flatten();
+        let klass = factory.and_then(|factory| factory.get_metadata("klass"));
+        let decoder =
+            klass.is_some_and(|klass| klass.contains("Decoder") && !klass.contains("Subtitle"));
+        select_result.to_value_by_nick(if decoder { "skip" } else { "try" })
+    })?;
+    let sink = ElementFactory::make("appsink", None)?;
+    let sink = sink.dynamic_cast::<AppSink>().unwrap();
+    sink.set_property("sync", &false)?;
+    pipeline.add_many(&[&decodebin, sink.as_ref()])?;
+
+    // Other subtitle streams are left unlinked, which decodebin is fine
+    // with as long as one stream is linked.
+    let sink_pad = sink.get_static_pad("sink").unwrap();
+    let streams = AtomicU32::new(0);
+    decodebin.connect_pad_added({
+        let sink_pad = sink_pad.clone();
+        move |_, pad| {
+            let i = streams.fetch_add(1, Ordering::SeqCst);
+            if i == index {
+                let _ = pad.link(&sink_pad);
+            }
+        }
+    });
+    // Without the stream, nothing would end the wait for cues.
+    decodebin.connect_no_more_pads({
+        let sink_pad = sink_pad.clone();
+        move |_| {
+            if !sink_pad.is_linked() {
+                sink_pad.send_event(gst::event::Eos::new());
+            }
+        }
+    });
+
+    let _auto_reset_pipeline = AutoStateReset(pipeline.clone());
+    pipeline
+        .set_state(State::Playing)
+        .context("failed to open subtitles")?;
+    let bus = pipeline.get_bus().unwrap();
+    let mut cues = Vec::new();
+    let mut last_cue = Instant::now();
+    loop {
+        if let Some(sample) = sink.try_pull_sample(POLL_INTERVAL.into()) {
+            cues.extend(Cue::from_sample(&sample));
+            last_cue = Instant::now();
+            continue;
+        }
+        if sink.is_eos() {
+            break;
+        }
+        if let Some(msg) = bus.pop_filtered(&[MessageType::Error]) {
+            // Decodebin fails when it has no stream to expose.
+            if !sink_pad.is_linked() {
+                return Ok(None);
+            }
+            if let MessageView::Error(err) = msg.view() {
+                bail!("failed to read subtitles: {}", err.get_error());
+            }
+        }
+        ensure!(last_cue.elapsed() < timeout, "timed out reading subtitles");
+    }
+    if !sink_pad.is_linked() {
+        return Ok(None);
+    }
+
+    // Cues without a duration last until the next one, which may be empty
+    // to clear them.
+    cues.sort_by_key(|cue| cue.start);
+    for i in 1..cues.len() {
+        if cues[i - 1].end.is_none() {
+            cues[i - 1].end = Some(cues[i].start);
+        }
+    }
+    cues.retain(|cue| !cue.text.is_empty());
+    Ok(Some(Subtitles { cues }))
+}
+
+/// Find a subtitle file with the same name as a local video, e.g.
+/// `movie.srt` for `movie.mkv`.
+fn find_sidecar(source: &str) -> Option<String> {
+    let path = Url::parse(source).ok()?.to_file_path().ok()?;
+    let path = SIDECAR_EXTENSIONS
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|path| path.is_file())?;
+    Url::from_file_path(path).ok().map(String::from)
+}
+
+/// Turn Pango markup, which subtitles with styling are converted to, into
+/// plain text.
+fn strip_markup(markup: &str) -> String {
+    let mut text = String::new();
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = match rest[start..].find('>') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,152 @@
+//! Explicit timestamps to sample at, e.g. from an edit decision list.
+
+use anyhow::{bail, Context, Result};
+use gst::ClockTime;
+use std::fmt;
+
+/// Position to sample at, either as a time or a frame number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    Time(ClockTime),
+    Frame(u64),
+}
+
+/// Parse a timestamp like `1:02:03.5`, `2:03` or `123.5`, or a frame
+/// number like `f1234`.
+pub fn parse_timestamp(text: &str) -> Result<Timestamp> {
+    let text = text.trim();
+    if let Some(frame) = text.strip_prefix(|c| c == 'f' || c == 'F') {
+        let frame = frame
+            .parse()
+            .with_context(|| format!("invalid frame number {:?}", text))?;
+        return Ok(Timestamp::Frame(frame));
+    }
+    let mut seconds = 0.;
+    for (i, part) in text.split(':').enumerate() {
+        if i > 2 {
+            bail!("too many components in timestamp {:?}", text);
+        }
+        let value = part
+            .parse::<f64>()
+            .ok()
+            .filter(|value| *value >= 0. && value.is_finite())
+            .with_context(|| format!("invalid timestamp {:?}", text))?;
+        seconds = seconds * 60. + value;
+    }
+    Ok(Timestamp::Time(ClockTime::from_nseconds(
+        (seconds * 1e9) as u64,
+    )))
+}
+
+/// Parse timestamps typed as a list separated by commas, semicolons or
+/// whitespace.
+pub fn parse_timestamps(text: &str) -> Result<Vec<Timestamp>> {
+    text.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(parse_timestamp)
+        .collect()
+}
+
+/// Parse timestamps from a text or CSV file, one per line in the first
+/// column. Empty lines, lines starting with `#` and a header are skipped.
+pub fn parse_timestamp_file(text: &str) -> Result<Vec<Timestamp>> {
+    let mut result = Vec::new();
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for (i, line) in lines.enumerate() {
+        let field = line.split(',').next().unwrap().trim().trim_matches('"');
+        match parse_timestamp(field) {
+            Ok(timestamp) => result.push(timestamp),
+            Err(_) if i == 0 => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(result)
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timestamp::Time(time) => f.write_str(&format_timestamp(*time)),
+            Timestamp::Frame(frame) => write!(f, "f{}", frame),
+        }
+    }
+}
+
+/// Format a time in a form `parse_timestamp` accepts.
+pub fn format_timestamp(timestamp: ClockTime) -> String {
+    let ms = timestamp.mseconds().unwrap_or_default();
+    let (hours, minutes, seconds, ms) =
+        (ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}.{:03}", hours, minutes, seconds, ms)
+    } else {
+        format!("{}:{:02}.{:03}", minutes, seconds, ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(ms: u64) -> Timestamp {
+        Timestamp::Time(ClockTime::from_mseconds(ms))
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_timestamp("1:02:03.5").unwrap(), time(3_723_500));
+        assert_eq!(parse_timestamp("2:03").unwrap(), time(123_000));
+        assert_eq!(parse_timestamp(" 123.5 ").unwrap(), time(123_500));
+    }
+
+    #[test]
+    fn parses_frame_numbers() {
+        assert_eq!(parse_timestamp("f1234").unwrap(), Timestamp::Frame(1234));
+        assert_eq!(parse_timestamp("F0").unwrap(), Timestamp::Frame(0));
+        assert!(parse_timestamp("f").is_err());
+        assert!(parse_timestamp("f1.5").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for text in &["", "1:2:3:4", "-1", "1:x", "inf"] {
+            assert!(parse_timestamp(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_lists() {
+        assert_eq!(
+            parse_timestamps("1, 2;f3\n4").unwrap(),
+            vec![time(1000), time(2000), Timestamp::Frame(3), time(4000)],
+        );
+    }
+
+    #[test]
+    fn skips_csv_header_and_comments() {
+        let text = "timestamp,note\n# intro\n\n0:01,\"a, b\"\n\"f25\",x\r\n";
+        assert_eq!(
+            parse_timestamp_file(text).unwrap(),
+            vec![time(1000), Timestamp::Frame(25)],
+        );
+    }
+
+    #[test]
+    fn fails_on_invalid_lines_after_the_first() {
+        assert!(parse_timestamp_file("0:01\nnope\n").is_err());
+    }
+
+    #[test]
+    fn formats_timestamps_it_parses() {
+        let time = ClockTime::from_mseconds(3_723_500);
+        assert_eq!(format_timestamp(time), "1:02:03.500");
+        assert_eq!(
+            parse_timestamp(&format_timestamp(time)).unwrap(),
+            Timestamp::Time(time),
+        );
+        assert_eq!(format_timestamp(ClockTime::from_mseconds(5)), "0:00.005");
+    }
+}
@@ -0,0 +1,96 @@
+//! Finding videos in folders.
+
+use gio::prelude::*;
+use gio::FileQueryInfoFlags;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which files to add from folders.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FolderScan {
+    /// Glob patterns like `*.mkv` of paths in folders to add, or none to
+    /// add all videos. Paths are relative to the folder added, and `*`
+    /// matches `/` as well.
+    pub include: Vec<String>,
+    /// Glob patterns of paths in folders to skip, which skip the whole
+    /// subfolder when matching one.
+    pub exclude: Vec<String>,
+}
+
+impl FolderScan {
+    fn includes(&self, path: &str) -> bool {
+        self.include.is_empty() || matches_any(&self.include, path)
+    }
+
+    fn excludes(&self, path: &str) -> bool {
+        matches_any(&self.exclude, path)
+    }
+}
+
+fn matches_any(patterns: &[String], path: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glib::pattern_match_simple(pattern, path))
+}
+
+/// Find the videos in the folder and its subfolders, in the order of their
+/// paths.
+pub fn scan_folder(folder: &Path, config: &FolderScan) -> Vec<PathBuf> {
+    let mut videos = Vec::new();
+    scan(folder, folder, config, &mut videos);
+    videos
+}
+
+fn scan(root: &Path, folder: &Path, config: &FolderScan, videos: &mut Vec<PathBuf>) {
+    // Folders which can't be read are skipped like files which aren't
+    // videos.
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut entries = entries.filter_map(|entry| entry.ok()).collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let relative = relative.to_string_lossy();
+        if config.excludes(&relative) {
+            continue;
+        }
+        // Symbolic links to folders aren't followed, which could loop.
+        let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+        if is_dir {
+            scan(root, &path, config, videos);
+        } else if path.is_file() && config.includes(&relative) && is_video(&path) {
+            videos.push(path);
+        }
+    }
+}
+
+/// Find the videos in a folder, or the file itself if it's a video.
+pub fn find_videos(path: &Path, config: &FolderScan) -> Vec<PathBuf> {
+    if path.is_dir() {
+        scan_folder(path, config)
+    } else if path.is_file() && is_video(path) {
+        vec![path.to_owned()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Check whether the file is a video by its content type, which GIO guesses
+/// from both the name and the content.
+pub fn is_video(path: &Path) -> bool {
+    let file = gio::File::new_for_path(path);
+    let info = file.query_info(
+        "standard::content-type",
+        FileQueryInfoFlags::NONE,
+        gio::NONE_CANCELLABLE,
+    );
+    info.ok()
+        .and_then(|info| info.get_content_type())
+        .and_then(|content_type| gio::content_type_get_mime_type(&content_type))
+        .is_some_and(|mime_type| mime_type.starts_with("video/"))
+}
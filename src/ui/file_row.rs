@@ -1,9 +1,10 @@
+use crate::probe::{MediaInfo, ProbeError};
+use crate::sampler::Timestamp;
 use glib::subclass::prelude::*;
 use glib::subclass::simple::{ClassStruct, InstanceStruct};
 use glib::translate::{FromGlibPtrFull, ToGlib, ToGlibPtr};
 use glib::types::StaticType;
-use glib::{glib_wrapper, Cast, Object};
-use std::path::Path;
+use glib::{glib_wrapper, Cast, Object, ObjectExt};
 use std::rc::Rc;
 
 glib_wrapper! {
@@ -30,27 +31,96 @@ impl FileRow {
         imp::FileRow::from_instance(self)
     }
 
-    pub fn set_path(&self, path: &Path) {
-        self.imp().path.replace(Rc::from(path));
+    pub fn set_uri(&self, uri: &str) {
+        self.imp().uri.replace(Rc::from(uri));
     }
 
-    pub fn get_path(&self) -> Rc<Path> {
-        self.imp().path.clone().into_inner()
+    pub fn get_uri(&self) -> Rc<str> {
+        self.imp().uri.clone().into_inner()
+    }
+
+    /// Record the result of probing the file.
+    pub fn set_media_info(&self, result: Result<Rc<MediaInfo>, &ProbeError>) {
+        match result {
+            Ok(media_info) => {
+                // Set the media info first so that it's available when
+                // the info property is notified.
+                let summary = media_info.summary();
+                self.imp().media_info.replace(Some(media_info));
+                self.set_property("info", &summary).unwrap();
+            }
+            // Files missing plugins are still queued, so that sampling
+            // reports the plugins to install.
+            Err(ProbeError::MissingPlugins(reason)) => {
+                let info = format!("Missing plugins: {}", reason);
+                self.set_property("info", &info).unwrap();
+            }
+            Err(ProbeError::Unplayable(reason)) => {
+                let info = format!("Unplayable: {}", reason);
+                self.set_property("info", &info).unwrap();
+                self.set_property("playable", &false).unwrap();
+            }
+        }
+    }
+
+    pub fn get_media_info(&self) -> Option<Rc<MediaInfo>> {
+        self.imp().media_info.borrow().clone()
+    }
+
+    /// Set the index of the video stream to sample, or `None` to use the
+    /// default selection.
+    pub fn set_stream(&self, stream: Option<u32>) {
+        self.imp().stream.set(stream);
+    }
+
+    pub fn get_stream(&self) -> Option<u32> {
+        self.imp().stream.get()
+    }
+
+    /// Set the timestamps to sample at, or `None` to sample randomly.
+    pub fn set_timestamps(&self, timestamps: Option<Vec<Timestamp>>) {
+        self.imp().timestamps.replace(timestamps);
+    }
+
+    pub fn get_timestamps(&self) -> Option<Vec<Timestamp>> {
+        self.imp().timestamps.borrow().clone()
+    }
+
+    /// Whether the file is playable. Files not yet probed are assumed to be.
+    pub fn is_playable(&self) -> bool {
+        self.imp().playable.get()
     }
 }
 
 mod imp {
+    use crate::probe::MediaInfo;
+    use crate::sampler::Timestamp;
     use glib::subclass::prelude::*;
     use glib::subclass::simple::{ClassStruct, InstanceStruct};
-    use glib::{glib_object_impl, glib_object_subclass, Object};
-    use std::cell::RefCell;
-    use std::path::Path;
+    use glib::subclass::Property;
+    use glib::{glib_object_impl, glib_object_subclass, ToValue};
+    use glib::{Object, ParamFlags, ParamSpec, Value};
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
 
     pub struct FileRow {
-        pub path: RefCell<Rc<Path>>,
+        pub uri: RefCell<Rc<str>>,
+        pub media_info: RefCell<Option<Rc<MediaInfo>>>,
+        pub stream: Cell<Option<u32>>,
+        pub timestamps: RefCell<Option<Vec<Timestamp>>>,
+        info: RefCell<Option<String>>,
+        pub playable: Cell<bool>,
     }
 
+    static PROPERTIES: &[Property] = &[
+        Property("info", |name| {
+            ParamSpec::string(name, "Info", "Info", None, ParamFlags::READWRITE)
+        }),
+        Property("playable", |name| {
+            ParamSpec::boolean(name, "Playable", "Playable", true, ParamFlags::READWRITE)
+        }),
+    ];
+
     impl ObjectSubclass for FileRow {
         const NAME: &'static str = "FileRow";
         type ParentType = Object;
@@ -59,14 +129,45 @@ mod imp {
 
         glib_object_subclass!();
 
+        fn class_init(klass: &mut Self::Class) {
+            klass.install_properties(PROPERTIES);
+        }
+
         fn new() -> Self {
             FileRow {
-                path: RefCell::new(Rc::from(Path::new(""))),
+                uri: RefCell::new(Rc::from("")),
+                media_info: RefCell::new(None),
+                stream: Cell::new(None),
+                timestamps: RefCell::new(None),
+                info: RefCell::new(None),
+                playable: Cell::new(true),
             }
         }
     }
 
     impl ObjectImpl for FileRow {
         glib_object_impl!();
+
+        fn set_property(&self, _: &Object, id: usize, value: &Value) {
+            match &PROPERTIES[id] {
+                Property("info", ..) => {
+                    let info = value.get::<String>().expect("expected string");
+                    self.info.replace(info);
+                }
+                Property("playable", ..) => {
+                    let value = value.get().expect("expected bool").unwrap_or_default();
+                    self.playable.replace(value);
+                }
+                _ => unreachable!("unknown property"),
+            }
+        }
+
+        fn get_property(&self, _: &Object, id: usize) -> Result<Value, ()> {
+            match &PROPERTIES[id] {
+                Property("info", ..) => Ok(self.info.borrow().to_value()),
+                Property("playable", ..) => Ok(self.playable.get().to_value()),
+                _ => unreachable!("unknown property"),
+            }
+        }
     }
 }
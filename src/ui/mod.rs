@@ -1,6 +1,14 @@
 use self::file_row::FileRow;
 use self::queue_row::QueueRow;
-use crate::sampler::Task;
+use self::review::ReviewAction;
+use crate::plugins;
+use crate::probe::ProbeResult;
+use crate::sampler::{
+    self, parse_timestamp_file, parse_timestamps, ChapterSampling, ColorConfig, DeinterlaceConfig,
+    HdrConfig, LiveCapture, OutputFormat, OverlayConfig, SeekMode, StreamSelection, SubtitleConfig,
+    Task, TimeConstraints, Timestamp,
+};
+use crate::scan::{self, FolderScan};
 use crate::{resource_path, Config};
 use gdk::DragAction;
 use gdk_pixbuf::Pixbuf;
@@ -8,25 +16,45 @@ use gio::prelude::*;
 use glib::{GString, MainContext, PRIORITY_DEFAULT};
 use gtk::prelude::*;
 use gtk::{
-    Adjustment, Align, Button, DestDefaults, Entry, FileChooserButton, Label, ListBox, ProgressBar,
-    TargetEntry, TargetFlags, Window,
+    Adjustment, Align, Button, ComboBoxText, DestDefaults, Entry, FileChooserAction,
+    FileChooserButton, FileChooserDialog, FileFilter, IconSize, Image, Label, ListBox, Orientation,
+    ProgressBar, ReliefStyle, ResponseType, ScrolledWindow, TargetEntry, TargetFlags, Window,
 };
 use pango::EllipsizeMode;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::ffi::OsStr;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::thread;
 use url::Url;
 
 mod file_row;
+mod picker;
 mod queue_row;
+mod review;
 
 pub struct Progress {
     /// Task reference, see `Task::ref_idx`.
     pub ref_idx: u32,
-    pub progress: Option<f64>,
+    pub state: TaskState,
+}
+
+pub enum TaskState {
+    /// Fraction of the task done.
+    Running(f64),
+    /// A sample has been written to the path.
+    Sampled(PathBuf),
+    /// The task has finished, with warnings reported during it.
+    Finished(Vec<String>),
+    /// The task has failed for the given reason.
+    Failed {
+        reason: String,
+        /// Installer details of the plugins missing for the task.
+        missing_plugins: Vec<String>,
+    },
 }
 
 #[derive(Deserialize, Serialize)]
@@ -35,6 +63,34 @@ pub struct DefaultConfig {
     height: u32,
     samples: u32,
     target: Option<PathBuf>,
+    /// Video stream to sample unless one is chosen in the file list.
+    stream: StreamSelection,
+    /// How to capture from live and non-seekable sources.
+    live: LiveCapture,
+    /// Parts of videos to take samples from.
+    constraints: TimeConstraints,
+    /// How to take samples according to chapters.
+    chapters: ChapterSampling,
+    /// Template of output file names. Fields are `{prefix}`, `{index}`,
+    /// `{min}`, `{sec}`, `{ms}`, `{frame}` and `{chapter}`.
+    name_template: String,
+    /// How to seek to sample positions.
+    seek_mode: SeekMode,
+    /// Whether to write a CSV manifest of the samples of each video.
+    manifest: bool,
+    /// Pixel format of the output images.
+    format: OutputFormat,
+    /// How to handle HDR sources.
+    hdr: HdrConfig,
+    /// Whether and how to deinterlace.
+    deinterlace: DeinterlaceConfig,
+    /// Colorimetry overrides and marking of output images.
+    color: ColorConfig,
+    /// Text to burn into samples.
+    overlay: OverlayConfig,
+    subtitles: SubtitleConfig,
+    /// Which files to add from folders.
+    folders: FolderScan,
 }
 
 impl Default for DefaultConfig {
@@ -43,6 +99,20 @@ impl Default for DefaultConfig {
             height: 360,
             samples: 5,
             target: None,
+            stream: StreamSelection::default(),
+            live: LiveCapture::default(),
+            constraints: TimeConstraints::default(),
+            chapters: ChapterSampling::default(),
+            name_template: "{prefix}-{index}-{min}-{sec}-{ms}".to_owned(),
+            seek_mode: SeekMode::default(),
+            manifest: false,
+            format: OutputFormat::default(),
+            hdr: HdrConfig::default(),
+            deinterlace: DeinterlaceConfig::default(),
+            color: ColorConfig::default(),
+            overlay: OverlayConfig::default(),
+            subtitles: SubtitleConfig::default(),
+            folders: FolderScan::default(),
         }
     }
 }
@@ -50,16 +120,22 @@ impl Default for DefaultConfig {
 pub struct UiOpt {
     pub config: Rc<RefCell<Config>>,
     pub task_sender: crossbeam_channel::Sender<Task>,
+    pub probe_sender: crossbeam_channel::Sender<Box<str>>,
+    /// URIs of videos to put in the file list initially.
+    pub sources: Vec<String>,
 }
 
 pub struct UiRes {
     pub progress_sender: glib::Sender<Progress>,
+    pub probe_result_sender: glib::Sender<ProbeResult>,
 }
 
 pub fn init(opt: UiOpt) -> UiRes {
     let UiOpt {
         config,
         task_sender,
+        probe_sender,
+        sources,
     } = opt;
 
     let builder = gtk::Builder::from_resource(resource_path!("/main.glade"));
@@ -70,6 +146,8 @@ pub fn init(opt: UiOpt) -> UiRes {
     let file_target: FileChooserButton = builder.get_object("file_target").unwrap();
     let list_files: ListBox = builder.get_object("list_files").unwrap();
     let list_queue: ListBox = builder.get_object("list_queue").unwrap();
+    let button_add_files: Button = builder.get_object("button_add_files").unwrap();
+    let button_add_folder: Button = builder.get_object("button_add_folder").unwrap();
     let button_clear: Button = builder.get_object("button_clear").unwrap();
     let button_queue: Button = builder.get_object("button_queue").unwrap();
 
@@ -94,56 +172,111 @@ pub fn init(opt: UiOpt) -> UiRes {
     window.show_all();
 
     let files = gio::ListStore::new(FileRow::static_type());
-    list_files.bind_model(Some(&files), |item| {
-        let path = item.downcast_ref::<FileRow>().unwrap().get_path();
-        let label = Label::new(file_name_str(&path));
-        label.set_halign(Align::Start);
-        label.set_ellipsize(EllipsizeMode::Middle);
-        label.show();
-        label.upcast()
+    list_files.bind_model(Some(&files), create_file_row_widget);
+    for uri in sources.iter() {
+        add_source(&files, &probe_sender, uri);
+    }
+    // Folders are scanned in the background, since sniffing the content
+    // type of every file may take a while.
+    let (scan_sender, scan_receiver) = MainContext::channel(PRIORITY_DEFAULT);
+    scan_receiver.attach(None, {
+        let files = files.downgrade();
+        let probe_sender = probe_sender.clone();
+        move |uri: String| {
+            let files = files.upgrade().unwrap();
+            add_source(&files, &probe_sender, &uri);
+            glib::Continue(true)
+        }
     });
     list_files.drag_dest_set(
         DestDefaults::ALL,
         &[TargetEntry::new("text/uri-list", TargetFlags::OTHER_APP, 0)],
         DragAction::COPY,
     );
+    // Dropped local files and folders are scanned like added folders, and
+    // other URIs are added as they are.
     list_files.connect_drag_data_received({
+        let config = Rc::downgrade(&config);
         let files = files.downgrade();
+        let probe_sender = probe_sender.clone();
+        let scan_sender = scan_sender.clone();
         move |_, _, _, _, selection, _, _| {
+            let config = config.upgrade().unwrap();
             let files = files.upgrade().unwrap();
+            let mut paths = Vec::new();
             for uri in selection.get_uris().iter() {
-                let path = match file_uri_to_path(uri) {
-                    Some(path) => path,
-                    None => continue,
-                };
-                if file_name_str(&path).is_none() {
-                    continue;
+                match file_uri_to_path(uri) {
+                    Some(path) => paths.push(path),
+                    None => add_source(&files, &probe_sender, uri),
                 }
-                let row = FileRow::new();
-                row.set_path(&path);
-                files.insert_sorted(&row, |a, b| {
-                    let a = a.downcast_ref::<FileRow>().unwrap().get_path();
-                    let b = b.downcast_ref::<FileRow>().unwrap().get_path();
-                    let a = file_name_str(&a);
-                    let b = file_name_str(&b);
-                    a.cmp(&b)
-                });
             }
+            let scan_config = config.borrow().default.folders.clone();
+            spawn_scan(paths, scan_config, scan_sender.clone());
+        }
+    });
+
+    button_add_files.connect_clicked({
+        let window = window.downgrade();
+        let files = files.downgrade();
+        let probe_sender = probe_sender.clone();
+        move |_| {
+            let window = window.upgrade().unwrap();
+            let files = files.upgrade().unwrap();
+            let dialog = FileChooserDialog::with_buttons(
+                Some("Add Files"),
+                Some(&window),
+                FileChooserAction::Open,
+                &[
+                    ("_Cancel", ResponseType::Cancel),
+                    ("_Add", ResponseType::Accept),
+                ],
+            );
+            dialog.set_select_multiple(true);
+            let filter = FileFilter::new();
+            filter.set_name(Some("Videos"));
+            filter.add_mime_type("video/*");
+            dialog.add_filter(&filter);
+            let uris = match dialog.run() {
+                ResponseType::Accept => dialog.get_uris(),
+                _ => Vec::new(),
+            };
+            dialog.close();
+            for uri in uris.iter() {
+                add_source(&files, &probe_sender, uri);
+            }
+        }
+    });
+
+    button_add_folder.connect_clicked({
+        let config = Rc::downgrade(&config);
+        let window = window.downgrade();
+        move |_| {
+            let config = config.upgrade().unwrap();
+            let window = window.upgrade().unwrap();
+            let dialog = FileChooserDialog::with_buttons(
+                Some("Add Folders"),
+                Some(&window),
+                FileChooserAction::SelectFolder,
+                &[
+                    ("_Cancel", ResponseType::Cancel),
+                    ("_Add", ResponseType::Accept),
+                ],
+            );
+            dialog.set_select_multiple(true);
+            let folders = match dialog.run() {
+                ResponseType::Accept => dialog.get_filenames(),
+                _ => Vec::new(),
+            };
+            dialog.close();
+            let scan_config = config.borrow().default.folders.clone();
+            spawn_scan(folders, scan_config, scan_sender.clone());
         }
     });
 
     let queue = gio::ListStore::new(QueueRow::static_type());
-    list_queue.bind_model(Some(&queue), |item| {
-        let builder = gtk::Builder::from_resource(resource_path!("/queue_row.glade"));
-        let progress: ProgressBar = builder.get_object("progress").unwrap();
-        let label_name: Label = builder.get_object("label_name").unwrap();
-        item.bind_property("name", &label_name, "label")
-            .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
-            .build();
-        item.bind_property("progress", &progress, "fraction")
-            .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
-            .build();
-        builder.get_object("box_row").unwrap()
+    list_queue.bind_model(Some(&queue), {
+        let task_sender = task_sender.clone();
+        move |item| create_queue_row_widget(item, &task_sender)
     });
 
     button_clear.connect_clicked({
@@ -188,27 +321,63 @@ pub fn init(opt: UiOpt) -> UiRes {
                 None => return,
             };
             let target_arc = Arc::<Path>::from(target.as_path());
+            let default_stream = config.borrow().default.stream.clone();
+            let live = config.borrow().default.live;
+            let constraints = config.borrow().default.constraints.clone();
+            let chapters = config.borrow().default.chapters;
+            let seek_mode = config.borrow().default.seek_mode;
+            let manifest = config.borrow().default.manifest;
+            let format = config.borrow().default.format;
+            let hdr = config.borrow().default.hdr;
+            let deinterlace = config.borrow().default.deinterlace.clone();
+            let color = config.borrow().default.color;
+            let overlay = config.borrow().default.overlay.clone();
+            let subtitles = config.borrow().default.subtitles;
+            let name_template = Arc::<str>::from(config.borrow().default.name_template.as_str());
             let ref_base = queue.get_n_items();
-            for i in 0..files.get_n_items() {
-                let file: FileRow = files.get_object(i).unwrap().downcast().unwrap();
-                let path = &*file.get_path();
-                let source = Box::from(path);
-                task_sender
-                    .send(Task {
-                        prefix: prefix.clone(),
-                        height,
-                        samples,
-                        target: target_arc.clone(),
-                        index: i + 1,
-                        source,
-                        ref_idx: ref_base + i,
-                    })
-                    .unwrap();
-                let name = file_name_str(path);
+            let playable_files = (0..files.get_n_items())
+                .map(|i| files.get_object(i).unwrap().downcast::<FileRow>().unwrap())
+                .filter(FileRow::is_playable)
+                .collect::<Vec<_>>();
+            for (i, file) in (0..).zip(playable_files.iter()) {
+                let uri = &*file.get_uri();
+                let source = Box::from(uri);
+                let name = uri_display_name(uri);
+                let stream = match file.get_stream() {
+                    Some(index) => StreamSelection::Index { index },
+                    None => default_stream.clone(),
+                };
+                let task = Task {
+                    prefix: prefix.clone(),
+                    height,
+                    samples,
+                    target: target_arc.clone(),
+                    index: i + 1,
+                    source,
+                    stream,
+                    live,
+                    constraints: constraints.clone(),
+                    timestamps: file.get_timestamps(),
+                    chapters,
+                    name_template: name_template.clone(),
+                    seek_mode,
+                    manifest,
+                    format,
+                    hdr,
+                    deinterlace: deinterlace.clone(),
+                    color,
+                    overlay: overlay.clone(),
+                    display_name: Box::from(name.as_str()),
+                    subtitles,
+                    replacing: false,
+                    ref_idx: ref_base + i,
+                };
                 let queue_row = QueueRow::new();
                 queue_row.set_property("name", &name).unwrap();
                 queue_row.set_property("progress", &0.).unwrap();
+                queue_row.set_task(task.clone());
                 queue.append(&queue_row);
+                task_sender.send(task).unwrap();
             }
             files.remove_all();
             // Save the config to default
@@ -226,13 +395,379 @@ pub fn init(opt: UiOpt) -> UiRes {
         move |progress: Progress| {
             let queue = queue.upgrade().unwrap();
             let row = queue.get_object(progress.ref_idx).unwrap();
-            row.set_property("progress", &progress.progress.unwrap_or_default())
-                .unwrap();
+            match progress.state {
+                TaskState::Running(progress) => row.set_property("progress", &progress).unwrap(),
+                TaskState::Sampled(path) => row.downcast::<QueueRow>().unwrap().add_sample(path),
+                TaskState::Finished(warnings) => {
+                    row.downcast_ref::<QueueRow>().unwrap().set_finished(true);
+                    if !warnings.is_empty() {
+                        row.set_property("warnings", &warnings.join("\n")).unwrap();
+                    }
+                }
+                TaskState::Failed {
+                    reason,
+                    missing_plugins,
+                } => {
+                    row.set_property("progress", &0.).unwrap();
+                    row.set_property("error", &reason).unwrap();
+                    if !missing_plugins.is_empty() && plugins::installation_supported() {
+                        row.set_property("missing-plugins", &missing_plugins.join("\n"))
+                            .unwrap();
+                    }
+                }
+            }
+            glib::Continue(true)
+        }
+    });
+
+    let (probe_result_sender, probe_result_receiver) = MainContext::channel(PRIORITY_DEFAULT);
+    probe_result_receiver.attach(None, {
+        let files = files.downgrade();
+        move |probe: ProbeResult| {
+            let files = files.upgrade().unwrap();
+            let result = probe.result.map(Rc::new);
+            for i in 0..files.get_n_items() {
+                let file: FileRow = files.get_object(i).unwrap().downcast().unwrap();
+                if *file.get_uri() == *probe.uri {
+                    let result = result.as_ref().map(Rc::clone);
+                    file.set_media_info(result);
+                }
+            }
             glib::Continue(true)
         }
     });
 
-    UiRes { progress_sender }
+    UiRes {
+        progress_sender,
+        probe_result_sender,
+    }
+}
+
+fn create_file_row_widget(item: &glib::Object) -> gtk::Widget {
+    let uri = item.downcast_ref::<FileRow>().unwrap().get_uri();
+    let label_name = Label::new(Some(&uri_display_name(&uri)));
+    label_name.set_tooltip_text(Some(&uri));
+    label_name.set_halign(Align::Start);
+    label_name.set_ellipsize(EllipsizeMode::Middle);
+    item.bind_property("playable", &label_name, "sensitive")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    let label_info = Label::new(None);
+    label_info.set_halign(Align::Start);
+    label_info.set_ellipsize(EllipsizeMode::End);
+    label_info.get_style_context().add_class("dim-label");
+    item.bind_property("info", &label_info, "label")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    item.bind_property("info", &label_info, "tooltip-text")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    // Let the user choose the video stream for files with multiple.
+    let combo_stream = ComboBoxText::new();
+    combo_stream.set_halign(Align::Start);
+    combo_stream.set_no_show_all(true);
+    combo_stream.connect_changed({
+        let item = item.downgrade();
+        move |combo| {
+            let item = item.upgrade().unwrap();
+            let file = item.downcast_ref::<FileRow>().unwrap();
+            let stream = combo
+                .get_active_id()
+                .and_then(|id| id.as_str().parse::<u32>().ok());
+            file.set_stream(stream);
+        }
+    });
+    item.connect_local("notify::info", false, {
+        let combo_stream = combo_stream.downgrade();
+        move |values| {
+            let combo_stream = combo_stream.upgrade()?;
+            let item = values[0].get::<FileRow>().ok()??;
+            let media_info = item.get_media_info()?;
+            if media_info.video_streams.len() < 2 {
+                return None;
+            }
+            combo_stream.remove_all();
+            combo_stream.append(Some(""), "Default video stream");
+            for (i, stream) in media_info.video_streams.iter().enumerate() {
+                let label = format!("Video stream #{}: {}", i + 1, stream.describe());
+                combo_stream.append(Some(&i.to_string()), &label);
+            }
+            combo_stream.set_active_id(Some(""));
+            combo_stream.show();
+            None
+        }
+    })
+    .unwrap();
+    // Let the user give the timestamps to sample at.
+    let entry_timestamps = Entry::new();
+    entry_timestamps.set_hexpand(true);
+    entry_timestamps.set_placeholder_text(Some("Random timestamps or frame numbers"));
+    entry_timestamps.connect_changed({
+        let item = item.downgrade();
+        move |entry| {
+            let item = item.upgrade().unwrap();
+            let file = item.downcast_ref::<FileRow>().unwrap();
+            let text = entry.get_text();
+            let timestamps = match parse_timestamps(&text) {
+                Ok(timestamps) if timestamps.is_empty() => None,
+                Ok(timestamps) => Some(timestamps),
+                Err(e) => {
+                    set_entry_error(entry, Some(&e.to_string()));
+                    file.set_timestamps(None);
+                    return;
+                }
+            };
+            set_entry_error(entry, None);
+            file.set_timestamps(timestamps);
+        }
+    });
+    let button_load = Button::from_icon_name(Some("document-open-symbolic"), IconSize::Button);
+    button_load.set_tooltip_text(Some("Load timestamps from a text or CSV file"));
+    button_load.connect_clicked({
+        let entry_timestamps = entry_timestamps.downgrade();
+        move |button| {
+            let entry = entry_timestamps.upgrade().unwrap();
+            let window = button
+                .get_toplevel()
+                .and_then(|toplevel| toplevel.downcast::<Window>().ok());
+            let dialog = FileChooserDialog::with_buttons(
+                Some("Load Timestamps"),
+                window.as_ref(),
+                FileChooserAction::Open,
+                &[
+                    ("_Cancel", ResponseType::Cancel),
+                    ("_Open", ResponseType::Accept),
+                ],
+            );
+            let path = match dialog.run() {
+                ResponseType::Accept => dialog.get_filename(),
+                _ => None,
+            };
+            dialog.close();
+            let path = match path {
+                Some(path) => path,
+                None => return,
+            };
+            let timestamps = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| parse_timestamp_file(&text));
+            match timestamps {
+                Ok(timestamps) => entry.set_text(&format_timestamps(&timestamps)),
+                Err(e) => {
+                    let error = format!("{}: {:#}", path.display(), e);
+                    set_entry_error(&entry, Some(&error));
+                }
+            }
+        }
+    });
+    let button_pick = Button::from_icon_name(Some("video-x-generic-symbolic"), IconSize::Button);
+    button_pick.set_tooltip_text(Some("Pick frames in a preview of the video"));
+    button_pick.connect_clicked({
+        let item = item.downgrade();
+        let entry_timestamps = entry_timestamps.downgrade();
+        move |button| {
+            let item = item.upgrade().unwrap();
+            let file = item.downcast_ref::<FileRow>().unwrap();
+            let entry = entry_timestamps.upgrade().unwrap();
+            let window = button
+                .get_toplevel()
+                .and_then(|toplevel| toplevel.downcast::<Window>().ok());
+            if let Some(timestamps) = picker::pick_frames(window.as_ref(), &file.get_uri()) {
+                entry.set_text(&format_timestamps(&timestamps));
+            }
+        }
+    });
+    let box_timestamps = gtk::Box::new(Orientation::Horizontal, 4);
+    box_timestamps.add(&entry_timestamps);
+    box_timestamps.add(&button_load);
+    box_timestamps.add(&button_pick);
+    let box_row = gtk::Box::new(Orientation::Vertical, 0);
+    box_row.add(&label_name);
+    box_row.add(&label_info);
+    box_row.add(&combo_stream);
+    box_row.add(&box_timestamps);
+    box_row.show_all();
+    box_row.upcast()
+}
+
+fn create_queue_row_widget(
+    item: &glib::Object,
+    task_sender: &crossbeam_channel::Sender<Task>,
+) -> gtk::Widget {
+    let builder = gtk::Builder::from_resource(resource_path!("/queue_row.glade"));
+    let progress: ProgressBar = builder.get_object("progress").unwrap();
+    let label_name: Label = builder.get_object("label_name").unwrap();
+    item.bind_property("name", &label_name, "label")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    item.bind_property("progress", &progress, "fraction")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    item.bind_property("error", &progress, "text")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .transform_to(|_, value| {
+            let error = value.get::<String>().ok()?;
+            Some(error.map(|_| "Failed").to_value())
+        })
+        .build();
+    let image_warning: Image = builder.get_object("image_warning").unwrap();
+    item.bind_property("warnings", &image_warning, "visible")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .transform_to(|_, value| {
+            let warnings = value.get::<String>().ok()?;
+            Some(warnings.is_some().to_value())
+        })
+        .build();
+    item.bind_property("warnings", &image_warning, "tooltip-text")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    let button_install: Button = builder.get_object("button_install").unwrap();
+    item.bind_property("missing-plugins", &button_install, "visible")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .transform_to(|_, value| {
+            let missing_plugins = value.get::<String>().ok()?;
+            Some(missing_plugins.is_some().to_value())
+        })
+        .build();
+    button_install.connect_clicked({
+        let item = item.downgrade();
+        move |_| {
+            let item = item.upgrade().unwrap();
+            let missing_plugins = item.get_property("missing-plugins").unwrap();
+            let missing_plugins = match missing_plugins.get::<String>().unwrap() {
+                Some(missing_plugins) => missing_plugins,
+                None => return,
+            };
+            let details = missing_plugins
+                .lines()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            // Hide the button while the installer is running.
+            item.set_property("missing-plugins", &None::<String>)
+                .unwrap();
+            let started = plugins::install(&details, {
+                let item = item.downgrade();
+                let missing_plugins = missing_plugins.clone();
+                move |installed| {
+                    let item = match item.upgrade() {
+                        Some(item) => item,
+                        None => return,
+                    };
+                    if installed {
+                        let message = "Plugins installed, queue the file again";
+                        item.set_property("error", &message).unwrap();
+                    } else {
+                        item.set_property("missing-plugins", &missing_plugins)
+                            .unwrap();
+                    }
+                }
+            });
+            if !started {
+                item.set_property("missing-plugins", &missing_plugins)
+                    .unwrap();
+            }
+        }
+    });
+    // Show thumbnails of the samples as they are written.
+    let scrolled_samples: ScrolledWindow = builder.get_object("scrolled_samples").unwrap();
+    let box_samples: gtk::Box = builder.get_object("box_samples").unwrap();
+    item.connect_local("notify::n-samples", false, {
+        let scrolled_samples = scrolled_samples.downgrade();
+        let box_samples = box_samples.downgrade();
+        move |values| {
+            let scrolled_samples = scrolled_samples.upgrade()?;
+            let box_samples = box_samples.upgrade()?;
+            let item = values[0].get::<QueueRow>().ok()??;
+            let samples = item.get_samples();
+            // Start over when samples have been removed.
+            let mut shown = box_samples.get_children();
+            if shown.len() > samples.len() {
+                for child in shown.drain(..) {
+                    box_samples.remove(&child);
+                }
+            }
+            for path in samples.iter().skip(shown.len()) {
+                let thumbnail = create_thumbnail(path);
+                thumbnail.show_all();
+                box_samples.add(&thumbnail);
+            }
+            scrolled_samples.show();
+            None
+        }
+    })
+    .unwrap();
+    // Let the user review the samples once the task is done.
+    let button_review: Button = builder.get_object("button_review").unwrap();
+    item.bind_property("progress", &button_review, "visible")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .transform_to(|_, value| {
+            let progress = value.get_some::<f64>().ok()?;
+            Some((progress >= 1.).to_value())
+        })
+        .build();
+    button_review.connect_clicked({
+        let item = item.downgrade();
+        let task_sender = task_sender.clone();
+        move |button| {
+            let item = item.upgrade().unwrap();
+            let row = item.downcast_ref::<QueueRow>().unwrap();
+            // The worker may still be writing samples and the manifest.
+            if !row.is_finished() {
+                return;
+            }
+            let task = match row.get_task() {
+                Some(task) => task,
+                None => return,
+            };
+            let window = button
+                .get_toplevel()
+                .and_then(|toplevel| toplevel.downcast::<Window>().ok());
+            let (rejected, replace) =
+                match review::review_samples(window.as_ref(), &row.get_samples()) {
+                    Some(ReviewAction::Delete(rejected)) => (rejected, false),
+                    Some(ReviewAction::Replace(rejected)) => (rejected, true),
+                    None => return,
+                };
+            let result = sampler::remove_samples(&task, &rejected);
+            row.remove_samples(&rejected);
+            if let Err(e) = result {
+                row.set_property("error", &format!("{:#}", e)).unwrap();
+                return;
+            }
+            if !replace {
+                return;
+            }
+            // Take the replacements from new random offsets into the same
+            // manifest, and show them in the same row.
+            let replacement = Task {
+                samples: rejected.len() as u32,
+                timestamps: None,
+                chapters: ChapterSampling::Ignore,
+                replacing: true,
+                ..task
+            };
+            row.set_finished(false);
+            row.set_property("progress", &0.).unwrap();
+            row.set_property("error", &None::<String>).unwrap();
+            row.set_property("warnings", &None::<String>).unwrap();
+            task_sender.send(replacement).unwrap();
+        }
+    });
+    let box_row: gtk::Box = builder.get_object("box_row").unwrap();
+    item.bind_property("error", &box_row, "tooltip-text")
+        .flags(glib::BindingFlags::DEFAULT | glib::BindingFlags::SYNC_CREATE)
+        .build();
+    box_row.upcast()
+}
+
+/// Mark the entry as having invalid input, with the error as its tooltip.
+fn set_entry_error(entry: &Entry, error: Option<&str>) {
+    let style = entry.get_style_context();
+    match error {
+        Some(_) => style.add_class("error"),
+        None => style.remove_class("error"),
+    }
+    entry.set_tooltip_text(error);
 }
 
 fn file_uri_to_path(uri: &GString) -> Option<PathBuf> {
@@ -244,6 +779,126 @@ fn file_uri_to_path(uri: &GString) -> Option<PathBuf> {
     }
 }
 
-fn file_name_str(path: &Path) -> Option<&str> {
-    path.file_name().and_then(OsStr::to_str)
+/// Find the videos in the files and folders in the background, and send
+/// their URIs.
+fn spawn_scan(paths: Vec<PathBuf>, config: FolderScan, sender: glib::Sender<String>) {
+    if paths.is_empty() {
+        return;
+    }
+    thread::spawn(move || {
+        for path in paths {
+            for video in scan::find_videos(&path, &config) {
+                if let Ok(url) = Url::from_file_path(&video) {
+                    let _ = sender.send(String::from(url));
+                }
+            }
+        }
+    });
+}
+
+/// Add a video to the file list unless it's already there, and start
+/// probing it.
+fn add_source(
+    files: &gio::ListStore,
+    probe_sender: &crossbeam_channel::Sender<Box<str>>,
+    uri: &str,
+) {
+    if !is_supported_uri(uri) {
+        return;
+    }
+    // Local files are identified by their canonical path, so that links
+    // and other paths to the same file don't add it twice.
+    let uri = canonical_uri(uri);
+    let uri = uri.as_str();
+    let exists = (0..files.get_n_items()).any(|i| {
+        let row = files.get_object(i).unwrap().downcast::<FileRow>().unwrap();
+        &*row.get_uri() == uri
+    });
+    if exists {
+        return;
+    }
+    let row = FileRow::new();
+    row.set_uri(uri);
+    files.insert_sorted(&row, |a, b| {
+        let a = a.downcast_ref::<FileRow>().unwrap().get_uri();
+        let b = b.downcast_ref::<FileRow>().unwrap().get_uri();
+        let a = uri_display_name(&a);
+        let b = uri_display_name(&b);
+        a.cmp(&b)
+    });
+    probe_sender.send(Box::from(uri)).unwrap();
+}
+
+/// Get the URI of the canonical path of a local file, or the URI itself
+/// otherwise.
+fn canonical_uri(uri: &str) -> String {
+    Url::parse(uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .and_then(|path| fs::canonicalize(path).ok())
+        .and_then(|path| Url::from_file_path(path).ok())
+        .map(String::from)
+        .unwrap_or_else(|| uri.to_owned())
+}
+
+/// Check whether GStreamer has a source element handling the URI.
+fn is_supported_uri(uri: &str) -> bool {
+    gst::Element::make_from_uri(gst::URIType::Src, uri, None).is_ok()
+}
+
+/// Format timestamps for the timestamps entry.
+fn format_timestamps(timestamps: &[Timestamp]) -> String {
+    timestamps
+        .iter()
+        .map(|timestamp| timestamp.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Height of the thumbnails of samples in the queue.
+const THUMBNAIL_HEIGHT: i32 = 48;
+
+/// Load an image scaled to the height, or a placeholder if it can't be
+/// loaded.
+fn load_thumbnail(path: &Path, height: i32) -> Image {
+    match Pixbuf::from_file_at_scale(path, -1, height, true) {
+        Ok(pixbuf) => Image::from_pixbuf(Some(&pixbuf)),
+        Err(_) => Image::from_icon_name(Some("image-missing"), IconSize::Dialog),
+    }
+}
+
+/// Create a thumbnail of a sample which opens the sample when clicked.
+fn create_thumbnail(path: &Path) -> Button {
+    let image = load_thumbnail(path, THUMBNAIL_HEIGHT);
+    let button = Button::new();
+    button.add(&image);
+    button.set_relief(ReliefStyle::None);
+    button.set_tooltip_text(path.file_name().and_then(OsStr::to_str));
+    button.connect_clicked({
+        let path = path.to_owned();
+        move |_| {
+            // Open with the default image viewer.
+            if let Ok(uri) = Url::from_file_path(&path) {
+                let _ = gio::AppInfo::launch_default_for_uri(
+                    uri.as_str(),
+                    None::<&gio::AppLaunchContext>,
+                );
+            }
+        }
+    });
+    button
+}
+
+/// Get the unescaped last path segment of the URI as the name to show, or
+/// the whole URI if it has none, e.g. for `rtsp://camera:554`.
+fn uri_display_name(uri: &str) -> String {
+    let name = Url::parse(uri).ok().and_then(|url| {
+        let name = url.path_segments()?.rev().find(|s| !s.is_empty())?;
+        glib::uri_unescape_string(name, None)
+    });
+    match name {
+        Some(name) => name.into(),
+        None => uri.to_owned(),
+    }
 }
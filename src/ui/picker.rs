@@ -0,0 +1,214 @@
+//! Dialog to pick the frames to sample by hand.
+
+use crate::preview::{Preview, PreviewEvent};
+use crate::sampler::{format_timestamp, RawFrame, Timestamp};
+use gdk_pixbuf::{Colorspace, Pixbuf};
+use glib::{MainContext, PRIORITY_DEFAULT};
+use gst::ClockTime;
+use gtk::prelude::*;
+use gtk::{
+    Button, Dialog, DialogFlags, IconSize, Image, Label, Orientation, ResponseType, Scale, Window,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Show the video for the user to mark frames in with a seek slider and
+/// frame stepping, and return the timestamps of the marked frames, or none
+/// if nothing is marked.
+pub fn pick_frames(parent: Option<&Window>, uri: &str) -> Option<Vec<Timestamp>> {
+    let dialog = Dialog::with_buttons(
+        Some("Pick Frames"),
+        parent,
+        DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            ("_Use Marks", ResponseType::Accept),
+        ],
+    );
+    let image = Image::new();
+    image.set_vexpand(true);
+    let button_prev = Button::from_icon_name(Some("go-previous-symbolic"), IconSize::Button);
+    button_prev.set_tooltip_text(Some("Previous frame"));
+    let button_next = Button::from_icon_name(Some("go-next-symbolic"), IconSize::Button);
+    button_next.set_tooltip_text(Some("Next frame"));
+    let scale = Scale::with_range(Orientation::Horizontal, 0., 1., 0.001);
+    scale.set_draw_value(false);
+    scale.set_hexpand(true);
+    let label_pos = Label::new(None);
+    let button_mark = Button::with_mnemonic("_Mark");
+    button_mark.set_tooltip_text(Some("Mark the current frame"));
+    let button_clear = Button::with_mnemonic("C_lear Marks");
+    let label_marks = Label::new(Some("No frames marked"));
+    label_marks.set_line_wrap(true);
+    label_marks.set_xalign(0.);
+    label_marks.get_style_context().add_class("dim-label");
+
+    let box_seek = gtk::Box::new(Orientation::Horizontal, 4);
+    box_seek.add(&button_prev);
+    box_seek.add(&scale);
+    box_seek.add(&button_next);
+    box_seek.add(&label_pos);
+    let box_marks = gtk::Box::new(Orientation::Horizontal, 4);
+    box_marks.add(&button_mark);
+    box_marks.add(&button_clear);
+    box_marks.add(&label_marks);
+    let content = dialog.get_content_area();
+    content.set_spacing(4);
+    content.add(&image);
+    content.add(&box_seek);
+    content.add(&box_marks);
+    dialog.set_default_size(640, -1);
+    dialog.show_all();
+
+    let (sender, receiver) = MainContext::channel(PRIORITY_DEFAULT);
+    let preview = match Preview::new(uri, sender) {
+        Ok(preview) => Rc::new(preview),
+        Err(e) => {
+            label_pos.set_text(&format!("{:#}", e));
+            box_seek.set_sensitive(false);
+            box_marks.set_sensitive(false);
+            dialog.run();
+            dialog.close();
+            return None;
+        }
+    };
+    // Position and duration of the frame shown.
+    let current = Rc::new(Cell::new(None::<(ClockTime, ClockTime)>));
+    let marks = Rc::new(RefCell::new(Vec::<ClockTime>::new()));
+    // Whether the slider is being moved to the frame shown, rather than by
+    // the user.
+    let updating = Rc::new(Cell::new(false));
+
+    receiver.attach(None, {
+        let image = image.downgrade();
+        let scale = scale.downgrade();
+        let label_pos = label_pos.downgrade();
+        let preview = Rc::downgrade(&preview);
+        let current = current.clone();
+        let updating = updating.clone();
+        move |event| {
+            let (image, scale, label_pos, preview) = match (
+                image.upgrade(),
+                scale.upgrade(),
+                label_pos.upgrade(),
+                preview.upgrade(),
+            ) {
+                (Some(image), Some(scale), Some(label_pos), Some(preview)) => {
+                    (image, scale, label_pos, preview)
+                }
+                _ => return glib::Continue(false),
+            };
+            match event {
+                PreviewEvent::Frame(frame) => {
+                    let RawFrame {
+                        width,
+                        height,
+                        data,
+                        ..
+                    } = frame.frame;
+                    let pixbuf = Pixbuf::from_mut_slice(
+                        data,
+                        Colorspace::Rgb,
+                        false,
+                        8,
+                        width as i32,
+                        height as i32,
+                        width as i32 * 3,
+                    );
+                    image.set_from_pixbuf(Some(&pixbuf));
+                    label_pos.set_text(&format_timestamp(frame.pos));
+                    current.set(Some((frame.pos, frame.duration)));
+                    if let Some(duration) = preview.duration() {
+                        updating.set(true);
+                        scale.set_range(0., seconds(duration));
+                        scale.set_value(seconds(frame.pos));
+                        updating.set(false);
+                    }
+                }
+                PreviewEvent::Error(error) => label_pos.set_text(&error),
+            }
+            glib::Continue(true)
+        }
+    });
+    scale.connect_value_changed({
+        let preview = preview.clone();
+        move |scale| {
+            if !updating.get() {
+                let pos = ClockTime::from_nseconds((scale.get_value() * 1e9) as u64);
+                let _ = preview.seek(pos);
+            }
+        }
+    });
+    // Stepping seeks to just before the start of the frame shown, or to its
+    // end, which are within the neighbouring frames.
+    button_prev.connect_clicked({
+        let preview = preview.clone();
+        let current = current.clone();
+        move |_| {
+            let pos = current.get().and_then(|(pos, _)| pos.nseconds());
+            if let Some(pos) = pos.filter(|&pos| pos > 0) {
+                let _ = preview.seek(ClockTime::from_nseconds(pos - 1));
+            }
+        }
+    });
+    button_next.connect_clicked({
+        let preview = preview.clone();
+        let current = current.clone();
+        move |_| {
+            if let Some((pos, duration)) = current.get() {
+                if duration.is_some() {
+                    let _ = preview.seek(pos + duration);
+                }
+            }
+        }
+    });
+    button_mark.connect_clicked({
+        let current = current.clone();
+        let marks = marks.clone();
+        let label_marks = label_marks.downgrade();
+        move |_| {
+            let label_marks = label_marks.upgrade().unwrap();
+            let (pos, duration) = match current.get() {
+                Some(frame) => frame,
+                None => return,
+            };
+            // Mark the middle of the frame, so that the timestamp rounded
+            // to milliseconds still falls within it.
+            let mark = match duration.nseconds() {
+                Some(duration) => pos + ClockTime::from_nseconds(duration / 2),
+                None => pos,
+            };
+            let mut marks = marks.borrow_mut();
+            if let Err(i) = marks.binary_search(&mark) {
+                marks.insert(i, mark);
+            }
+            let text = marks
+                .iter()
+                .map(|&mark| format_timestamp(mark))
+                .collect::<Vec<_>>()
+                .join(", ");
+            label_marks.set_text(&text);
+        }
+    });
+    button_clear.connect_clicked({
+        let marks = marks.clone();
+        let label_marks = label_marks.downgrade();
+        move |_| {
+            marks.borrow_mut().clear();
+            label_marks.upgrade().unwrap().set_text("No frames marked");
+        }
+    });
+
+    let response = dialog.run();
+    dialog.close();
+    drop(preview);
+    let marks = marks.take();
+    if response != ResponseType::Accept || marks.is_empty() {
+        return None;
+    }
+    Some(marks.into_iter().map(Timestamp::Time).collect())
+}
+
+fn seconds(time: ClockTime) -> f64 {
+    time.nseconds().unwrap_or_default() as f64 / 1e9
+}
@@ -1,8 +1,10 @@
+use crate::sampler::Task;
 use glib::subclass::prelude::*;
 use glib::subclass::simple::{ClassStruct, InstanceStruct};
 use glib::translate::{FromGlibPtrFull, ToGlib, ToGlibPtr};
 use glib::types::StaticType;
-use glib::{glib_wrapper, Cast, Object};
+use glib::{glib_wrapper, Cast, Object, ObjectExt};
+use std::path::PathBuf;
 
 glib_wrapper! {
     pub struct QueueRow(Object<
@@ -23,19 +25,75 @@ impl QueueRow {
             .downcast()
             .unwrap()
     }
+
+    fn imp(&self) -> &imp::QueueRow {
+        imp::QueueRow::from_instance(self)
+    }
+
+    /// Record a sample written by the task.
+    pub fn add_sample(&self, path: PathBuf) {
+        let mut samples = self.imp().samples.borrow_mut();
+        samples.push(path);
+        let n_samples = samples.len() as u32;
+        // Release the borrow for handlers of the notification.
+        drop(samples);
+        self.set_property("n-samples", &n_samples).unwrap();
+    }
+
+    /// Paths of the samples written so far.
+    pub fn get_samples(&self) -> Vec<PathBuf> {
+        self.imp().samples.borrow().clone()
+    }
+
+    /// Forget samples which have been deleted.
+    pub fn remove_samples(&self, paths: &[PathBuf]) {
+        let mut samples = self.imp().samples.borrow_mut();
+        samples.retain(|path| !paths.contains(path));
+        let n_samples = samples.len() as u32;
+        drop(samples);
+        self.set_property("n-samples", &n_samples).unwrap();
+    }
+
+    /// Set the task the row is for, to take replacement samples with.
+    pub fn set_task(&self, task: Task) {
+        self.imp().task.replace(Some(task));
+    }
+
+    pub fn get_task(&self) -> Option<Task> {
+        self.imp().task.borrow().clone()
+    }
+
+    /// Record whether the task has finished, after which its samples may
+    /// be reviewed.
+    pub fn set_finished(&self, finished: bool) {
+        self.imp().finished.set(finished);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.imp().finished.get()
+    }
 }
 
 mod imp {
+    use crate::sampler::Task;
     use glib::subclass::prelude::*;
     use glib::subclass::simple::{ClassStruct, InstanceStruct};
     use glib::subclass::Property;
     use glib::{glib_object_impl, glib_object_subclass, ToValue};
     use glib::{Object, ParamFlags, ParamSpec, Value};
     use std::cell::{Cell, RefCell};
+    use std::path::PathBuf;
 
     pub struct QueueRow {
         name: RefCell<Box<str>>,
         progress: Cell<f64>,
+        error: RefCell<Option<String>>,
+        warnings: RefCell<Option<String>>,
+        missing_plugins: RefCell<Option<String>>,
+        pub samples: RefCell<Vec<PathBuf>>,
+        n_samples: Cell<u32>,
+        pub task: RefCell<Option<Task>>,
+        pub finished: Cell<bool>,
     }
 
     static PROPERTIES: &[Property] = &[
@@ -53,6 +111,32 @@ mod imp {
                 ParamFlags::READWRITE,
             )
         }),
+        Property("error", |name| {
+            ParamSpec::string(name, "Error", "Error", None, ParamFlags::READWRITE)
+        }),
+        Property("warnings", |name| {
+            ParamSpec::string(name, "Warnings", "Warnings", None, ParamFlags::READWRITE)
+        }),
+        Property("missing-plugins", |name| {
+            ParamSpec::string(
+                name,
+                "Missing plugins",
+                "Installer details of missing plugins",
+                None,
+                ParamFlags::READWRITE,
+            )
+        }),
+        Property("n-samples", |name| {
+            ParamSpec::uint(
+                name,
+                "Number of samples",
+                "Number of samples written",
+                0,
+                u32::MAX,
+                0,
+                ParamFlags::READWRITE,
+            )
+        }),
     ];
 
     impl ObjectSubclass for QueueRow {
@@ -71,6 +155,13 @@ mod imp {
             Self {
                 name: RefCell::new(String::new().into_boxed_str()),
                 progress: Cell::new(0.),
+                error: RefCell::new(None),
+                warnings: RefCell::new(None),
+                missing_plugins: RefCell::new(None),
+                samples: RefCell::new(Vec::new()),
+                n_samples: Cell::new(0),
+                task: RefCell::new(None),
+                finished: Cell::new(false),
             }
         }
     }
@@ -89,6 +180,22 @@ mod imp {
                     let value = value.get().expect("expected float").unwrap_or_default();
                     self.progress.replace(value);
                 }
+                Property("error", ..) => {
+                    let error = value.get::<String>().expect("expected string");
+                    self.error.replace(error);
+                }
+                Property("warnings", ..) => {
+                    let warnings = value.get::<String>().expect("expected string");
+                    self.warnings.replace(warnings);
+                }
+                Property("missing-plugins", ..) => {
+                    let missing_plugins = value.get::<String>().expect("expected string");
+                    self.missing_plugins.replace(missing_plugins);
+                }
+                Property("n-samples", ..) => {
+                    let value = value.get().expect("expected uint").unwrap_or_default();
+                    self.n_samples.replace(value);
+                }
                 _ => unreachable!("unknown property"),
             }
         }
@@ -97,6 +204,10 @@ mod imp {
             match &PROPERTIES[id] {
                 Property("name", ..) => Ok(self.name.borrow().to_value()),
                 Property("progress", ..) => Ok(self.progress.get().to_value()),
+                Property("error", ..) => Ok(self.error.borrow().to_value()),
+                Property("warnings", ..) => Ok(self.warnings.borrow().to_value()),
+                Property("missing-plugins", ..) => Ok(self.missing_plugins.borrow().to_value()),
+                Property("n-samples", ..) => Ok(self.n_samples.get().to_value()),
                 _ => unreachable!("unknown property"),
             }
         }
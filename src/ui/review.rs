@@ -0,0 +1,78 @@
+//! Dialog to review the samples of a task.
+
+use super::load_thumbnail;
+use gtk::prelude::*;
+use gtk::{
+    Dialog, DialogFlags, FlowBox, PolicyType, ResponseType, ScrolledWindow, SelectionMode,
+    ToggleButton, Window,
+};
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// Height of the samples in the review grid.
+const REVIEW_HEIGHT: i32 = 120;
+
+/// What to do with the samples rejected in a review.
+pub enum ReviewAction {
+    Delete(Vec<PathBuf>),
+    /// Delete them and take as many new samples.
+    Replace(Vec<PathBuf>),
+}
+
+/// Show the samples in a grid for the user to reject some of them, and
+/// return what to do with the rejected samples, or none if nothing is
+/// rejected.
+pub fn review_samples(parent: Option<&Window>, samples: &[PathBuf]) -> Option<ReviewAction> {
+    let dialog = Dialog::with_buttons(
+        Some("Review Samples"),
+        parent,
+        DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            ("_Delete Rejected", ResponseType::Reject),
+            ("_Replace Rejected", ResponseType::Accept),
+        ],
+    );
+    let grid = FlowBox::new();
+    grid.set_selection_mode(SelectionMode::None);
+    grid.set_homogeneous(true);
+    // Rejected samples are the toggled ones, which are dimmed.
+    let toggles = samples
+        .iter()
+        .map(|path| {
+            let image = load_thumbnail(path, REVIEW_HEIGHT);
+            let toggle = ToggleButton::new();
+            toggle.add(&image);
+            toggle.set_tooltip_text(path.file_name().and_then(OsStr::to_str));
+            toggle.connect_toggled(move |toggle| {
+                image.set_opacity(if toggle.get_active() { 0.3 } else { 1. });
+            });
+            grid.add(&toggle);
+            toggle
+        })
+        .collect::<Vec<_>>();
+    let scrolled = ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+    scrolled.set_policy(PolicyType::Never, PolicyType::Automatic);
+    scrolled.set_vexpand(true);
+    scrolled.add(&grid);
+    dialog.get_content_area().add(&scrolled);
+    dialog.set_default_size(800, 600);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let rejected = samples
+        .iter()
+        .zip(toggles.iter())
+        .filter(|(_, toggle)| toggle.get_active())
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    dialog.close();
+    if rejected.is_empty() {
+        return None;
+    }
+    match response {
+        ResponseType::Reject => Some(ReviewAction::Delete(rejected)),
+        ResponseType::Accept => Some(ReviewAction::Replace(rejected)),
+        _ => None,
+    }
+}